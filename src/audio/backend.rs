@@ -0,0 +1,101 @@
+use std::fs;
+
+use eyre::Result;
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::Context;
+
+use crate::config::config_struct::Config;
+
+/// Something that can play the game's sounds.
+pub trait AudioBackend {
+    /// `pitch` is a playback speed ratio and `volume` is relative to the configured volume.
+    fn play_plink(&mut self, pitch: f32, volume: f32);
+    fn start_drumroll(&mut self);
+    fn stop_drumroll(&mut self);
+    fn play_fanfare(&mut self);
+}
+
+/// Plays nothing, for `--mute` and for running without a window.
+pub struct NoAudio;
+
+impl AudioBackend for NoAudio {
+    fn play_plink(&mut self, _pitch: f32, _volume: f32) {}
+
+    fn start_drumroll(&mut self) {}
+
+    fn stop_drumroll(&mut self) {}
+
+    fn play_fanfare(&mut self) {}
+}
+
+/// Plays the sound files from the config through ggez. Any sound without a file is skipped.
+pub struct GgezAudio {
+    plink: Option<Source>,
+    drumroll: Option<Source>,
+    fanfare: Option<Source>,
+    volume: f32,
+}
+
+impl GgezAudio {
+    pub fn new(context: &mut Context, config: &Config) -> Result<Self> {
+        let mut drumroll = load_sound(context, &config.drumroll_sound, config.volume)?;
+        if let Some(drumroll) = drumroll.as_mut() {
+            drumroll.set_repeat(true);
+        }
+
+        Ok(Self {
+            plink: load_sound(context, &config.plink_sound, config.volume)?,
+            drumroll,
+            fanfare: load_sound(context, &config.fanfare_sound, config.volume)?,
+            volume: config.volume,
+        })
+    }
+}
+
+impl AudioBackend for GgezAudio {
+    fn play_plink(&mut self, pitch: f32, volume: f32) {
+        if let Some(plink) = self.plink.as_mut() {
+            plink.set_pitch(pitch);
+            plink.set_volume(self.volume * volume);
+            report(plink.play_detached());
+        }
+    }
+
+    fn start_drumroll(&mut self) {
+        if let Some(drumroll) = self.drumroll.as_mut() {
+            report(drumroll.play());
+        }
+    }
+
+    fn stop_drumroll(&mut self) {
+        if let Some(drumroll) = self.drumroll.as_mut() {
+            drumroll.stop();
+        }
+    }
+
+    fn play_fanfare(&mut self) {
+        if let Some(fanfare) = self.fanfare.as_mut() {
+            report(fanfare.play());
+        }
+    }
+}
+
+fn load_sound(context: &mut Context, path: &Option<String>, volume: f32) -> Result<Option<Source>> {
+    let path = if let Some(path) = path {
+        path
+    } else {
+        return Ok(None);
+    };
+
+    let data = SoundData::from_bytes(&fs::read(path)?);
+    let mut source = Source::from_data(context, data)?;
+    source.set_volume(volume);
+    Ok(Some(source))
+}
+
+/// A sound failing to play should never stop the drop, so errors are only reported.
+fn report(result: ggez::GameResult) {
+    if let Err(error) = result {
+        eprintln!("Could not play sound: {}", error);
+    }
+}
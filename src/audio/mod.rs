@@ -0,0 +1,165 @@
+use crossbeam::channel::Receiver;
+use rapier2d::geometry::ColliderHandle;
+
+use crate::config::config_struct::Config;
+use crate::draw_data::DataType;
+use crate::event_manager::event::{Event, EventKind};
+use crate::event_manager::EventManager;
+use crate::simulation::Simulation;
+
+pub use self::backend::{AudioBackend, GgezAudio, NoAudio};
+
+pub mod backend;
+
+/// Impacts at or above this speed play the plink at full volume and highest pitch.
+const LOUDEST_IMPACT_SPEED: f32 = 200.0;
+
+/// Decides when sounds should play based on what the simulation is doing, and leaves the
+/// actual playing to an `AudioBackend` so it can be swapped for `NoAudio` when there is no
+/// window or the user asked for `--mute`.
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    events: Option<Receiver<Event>>,
+    plink_min_steps: u64,
+    last_plink_step: Option<u64>,
+    idle_velocity_threshold: f32,
+    drumroll_playing: bool,
+    fanfare_played: bool,
+}
+
+impl Audio {
+    pub fn new(config: &Config, event_manager: &mut EventManager) -> Self {
+        let events = if config.mute {
+            None
+        } else {
+            let mut event_kinds = vec![
+                EventKind::FloorRemoved,
+                EventKind::WinnerDeclared,
+                EventKind::RoundReset,
+            ];
+            if config.plink_sound.is_some() {
+                event_kinds.push(EventKind::Contact);
+            }
+            Some(event_manager.subscribe_many(&event_kinds))
+        };
+
+        Self {
+            backend: Box::new(NoAudio),
+            events,
+            plink_min_steps: (config.plink_min_interval * config.steps_per_second as f32) as u64,
+            last_plink_step: None,
+            idle_velocity_threshold: config.idle_velocity_threshold,
+            drumroll_playing: false,
+            fanfare_played: false,
+        }
+    }
+
+    pub fn set_backend(&mut self, backend: Box<dyn AudioBackend>) {
+        self.backend = backend;
+    }
+
+    /// Catches up with the events since the last update without playing anything, for when
+    /// playback jumps to another step. Only the drumroll and fanfare state is kept in sync.
+    pub fn skip(&mut self) {
+        let events = if let Some(events) = &self.events {
+            events.try_iter().collect::<Vec<Event>>()
+        } else {
+            return;
+        };
+
+        for event in events {
+            match event {
+                Event::RoundReset => self.reset(),
+                Event::WinnerDeclared(_) => {
+                    self.backend.stop_drumroll();
+                    self.drumroll_playing = false;
+                    self.fanfare_played = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Called after every simulation step.
+    pub fn update(&mut self, simulation: &Simulation) {
+        let events = if let Some(events) = &self.events {
+            events.try_iter().collect::<Vec<Event>>()
+        } else {
+            return;
+        };
+
+        let mut fastest_impact: Option<f32> = None;
+        for event in events {
+            match event {
+                Event::RoundReset => {
+                    self.reset();
+                    fastest_impact = None;
+                }
+                Event::FloorRemoved(_) if !self.drumroll_playing && !self.fanfare_played => {
+                    self.backend.start_drumroll();
+                    self.drumroll_playing = true;
+                }
+                Event::WinnerDeclared(_) => {
+                    self.backend.stop_drumroll();
+                    self.drumroll_playing = false;
+                    if !self.fanfare_played {
+                        self.backend.play_fanfare();
+                        self.fanfare_played = true;
+                    }
+                }
+                Event::Contact(collider_handle1, collider_handle2, impact_speed)
+                    if is_nail_contact(simulation, collider_handle1, collider_handle2) =>
+                {
+                    fastest_impact = Some(fastest_impact.unwrap_or(0.0).max(impact_speed));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(speed) = fastest_impact {
+            let step = simulation.current_step();
+            let rate_limited = self
+                .last_plink_step
+                .map(|last_plink_step| step - last_plink_step < self.plink_min_steps)
+                .unwrap_or(false);
+            if !rate_limited && speed > self.idle_velocity_threshold {
+                let strength = (speed / LOUDEST_IMPACT_SPEED).min(1.0);
+                self.backend
+                    .play_plink(0.8 + strength * 0.6, 0.2 + strength * 0.8);
+                self.last_plink_step = Some(step);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.backend.stop_drumroll();
+        self.drumroll_playing = false;
+        self.fanfare_played = false;
+        self.last_plink_step = None;
+    }
+}
+
+/// Whether a contact was between a ball and a nail.
+fn is_nail_contact(
+    simulation: &Simulation,
+    collider_handle1: ColliderHandle,
+    collider_handle2: ColliderHandle,
+) -> bool {
+    let physics = &simulation.physics;
+    let ids = (
+        physics.get_id_by_collider_handle(collider_handle1),
+        physics.get_id_by_collider_handle(collider_handle2),
+    );
+    let (id1, id2) = match ids {
+        (Some(id1), Some(id2)) => (id1, id2),
+        _ => return false,
+    };
+    let types = (
+        simulation.draw_data.get_type(id1),
+        simulation.draw_data.get_type(id2),
+    );
+    matches!(
+        types,
+        (DataType::Ball, DataType::Nail) | (DataType::Nail, DataType::Ball)
+    )
+}
@@ -0,0 +1,94 @@
+use ggez::graphics::{Color, DrawMode, MeshBuilder};
+use ggez::GameResult;
+
+/// One more than the colour-blind-safe palette has colours, so shapes and colours drift apart
+/// instead of cycling together. With the ring, the first 72 choices all look different.
+const SHAPES: usize = 9;
+
+/// Draws a shape inside a ball so balls can be told apart without relying on colour. Every
+/// choice index gets its own combination of shape and outer ring. The pattern doesn't turn
+/// with the ball, otherwise a plus and a cross, or a square and a diamond, would look the same
+/// while it rolls.
+pub fn draw_pattern(
+    mesh_builder: &mut MeshBuilder,
+    index: usize,
+    position: [f32; 2],
+    radius: f32,
+    color: Color,
+) -> GameResult {
+    let size = radius * 0.55;
+    let width = (radius * 0.2).max(1.0);
+    let point = |angle: f32, distance: f32| {
+        [
+            position[0] + angle.cos() * distance,
+            position[1] + angle.sin() * distance,
+        ]
+    };
+    let regular_polygon = |corners: usize, offset: f32| -> Vec<[f32; 2]> {
+        (0..corners)
+            .map(|corner| {
+                let angle = std::f32::consts::PI * 2.0 * corner as f32 / corners as f32;
+                point(angle + offset, size)
+            })
+            .collect()
+    };
+    let quarter_turn = std::f32::consts::FRAC_PI_2;
+
+    match index % SHAPES {
+        0 => {
+            mesh_builder.circle(DrawMode::fill(), position, size * 0.6, 0.1, color);
+        }
+        1 => {
+            mesh_builder.circle(DrawMode::stroke(width), position, size * 0.7, 0.1, color);
+        }
+        2 => {
+            mesh_builder.line(&[point(0.0, size), point(0.0, -size)], width, color)?;
+            mesh_builder.line(
+                &[point(quarter_turn, size), point(quarter_turn, -size)],
+                width,
+                color,
+            )?;
+        }
+        3 => {
+            let eighth_turn = quarter_turn / 2.0;
+            mesh_builder.line(
+                &[point(eighth_turn, size), point(eighth_turn, -size)],
+                width,
+                color,
+            )?;
+            mesh_builder.line(
+                &[point(-eighth_turn, size), point(-eighth_turn, -size)],
+                width,
+                color,
+            )?;
+        }
+        4 => {
+            mesh_builder.polygon(DrawMode::fill(), &regular_polygon(3, -quarter_turn), color)?;
+        }
+        5 => {
+            mesh_builder.polygon(
+                DrawMode::fill(),
+                &regular_polygon(4, quarter_turn / 2.0),
+                color,
+            )?;
+        }
+        6 => {
+            mesh_builder.polygon(DrawMode::fill(), &regular_polygon(4, 0.0), color)?;
+        }
+        7 => {
+            mesh_builder.line(&[point(0.0, size), point(0.0, -size)], width, color)?;
+        }
+        _ => {
+            mesh_builder.line(
+                &[point(quarter_turn, size), point(quarter_turn, -size)],
+                width,
+                color,
+            )?;
+        }
+    }
+
+    if (index / SHAPES) % 2 == 1 {
+        mesh_builder.circle(DrawMode::stroke(width), position, radius * 0.85, 0.1, color);
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use ggez::event::KeyCode;
+use ggez::graphics::Rect;
+
+use crate::config::config_struct::Config;
+
+const PAN_SPEED: f32 = 50.0;
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 10.0;
+
+/// Which part of the board is on screen. The board can be larger than the window, so the
+/// camera either follows the lowest ball or is moved around by the user.
+pub struct Camera {
+    center_x: f32,
+    center_y: f32,
+    zoom: f32,
+    base_zoom: f32,
+    pub following: bool,
+    pub dragging: bool,
+}
+
+impl Camera {
+    pub fn new(config: &Config) -> Self {
+        let (window_width, window_height) = config.window_size();
+        let base_zoom = window_width / config.width;
+        Self {
+            center_x: config.width / 2.0,
+            center_y: window_height / base_zoom / 2.0,
+            zoom: base_zoom,
+            base_zoom,
+            following: config.camera_follow,
+            dragging: false,
+        }
+    }
+
+    /// The world rectangle to use as the screen coordinates for a window of the given size.
+    pub fn visible_rect(&self, window_width: f32, window_height: f32) -> Rect {
+        let width = window_width / self.zoom;
+        let height = window_height / self.zoom;
+        Rect::new(
+            self.center_x - width / 2.0,
+            self.center_y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    pub fn screen_to_world(
+        &self,
+        x: f32,
+        y: f32,
+        window_width: f32,
+        window_height: f32,
+    ) -> (f32, f32) {
+        let visible_rect = self.visible_rect(window_width, window_height);
+        (
+            visible_rect.x + x / self.zoom,
+            visible_rect.y + y / self.zoom,
+        )
+    }
+
+    /// Eases towards the lowest ball, zooming in once it is close to the finish.
+    pub fn follow(&mut self, lowest_ball: Option<(f32, f32)>, config: &Config) {
+        if !self.following {
+            return;
+        }
+
+        let (x, y) = if let Some(position) = lowest_ball {
+            position
+        } else {
+            return;
+        };
+        let y = y.min(config.height);
+        let target_zoom = if y > config.height * (1.0 - config.camera_finish_zone) {
+            self.base_zoom * config.camera_finish_zoom
+        } else {
+            self.base_zoom
+        };
+
+        let smoothing = config.camera_smoothing;
+        self.zoom += (target_zoom - self.zoom) * smoothing;
+        let (window_width, _) = config.window_size();
+        let half_width = window_width / self.zoom / 2.0;
+        let target_x = if half_width * 2.0 >= config.width {
+            config.width / 2.0
+        } else {
+            x.max(half_width).min(config.width - half_width)
+        };
+        self.center_x += (target_x - self.center_x) * smoothing;
+        self.center_y += (y - self.center_y) * smoothing;
+    }
+
+    /// Moves the camera by a distance in screen pixels.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.following = false;
+        self.center_x += dx / self.zoom;
+        self.center_y += dy / self.zoom;
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.following = false;
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub fn reset(&mut self, config: &Config) {
+        *self = Self::new(config);
+        self.following = true;
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, config: &Config) {
+        match keycode {
+            KeyCode::Up => self.pan(0.0, -PAN_SPEED),
+            KeyCode::Down => self.pan(0.0, PAN_SPEED),
+            KeyCode::Left => self.pan(-PAN_SPEED, 0.0),
+            KeyCode::Right => self.pan(PAN_SPEED, 0.0),
+            KeyCode::Equals | KeyCode::Add => self.zoom_by(ZOOM_STEP),
+            KeyCode::Minus | KeyCode::Subtract => self.zoom_by(1.0 / ZOOM_STEP),
+            KeyCode::F => self.following = !self.following,
+            KeyCode::Key0 => self.reset(config),
+            _ => {}
+        }
+    }
+}
@@ -1,28 +1,112 @@
-use std::fs::File;
-use std::io::Read;
+use std::fs::{read_to_string, File};
+use std::io::{Read, Write};
+use std::path::Path;
 
-use eyre::Result;
+use eyre::{bail, Result};
 use ggez::graphics::Color;
 use serde::{Deserialize, Serialize};
 
-use crate::helpers::serde_color;
+use crate::config::config_struct::Config;
+use crate::helpers::{serde_color, serde_tags};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// One option that gets a ball in the drop.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Choice {
     pub name: String,
-    #[serde(default = "default_color")]
-    pub red: u8,
-    #[serde(default = "default_color")]
-    pub green: u8,
-    #[serde(default = "default_color")]
-    pub blue: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub red: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub green: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blue: Option<u8>,
     #[serde(default = "default_alpha")]
     pub alpha: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub friction: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restitution: Option<f32>,
+    /// Path to a PNG or JPEG drawn inside the ball instead of the flat colour.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Free-form team or category, available as `{group}` in the winner message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Used by `--include-tag` and `--exclude-tag`.
+    #[serde(
+        default,
+        deserialize_with = "serde_tags::deserialize",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub tags: Vec<String>,
+    /// Choices marked unavailable are left out of the drop. Missing means available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
 }
 
 impl Choice {
-    pub fn color(&self) -> Color {
-        Color::from_rgba(self.red, self.green, self.blue, self.alpha)
+    /// A choice with only a name, using the config's defaults and an automatic colour.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            red: None,
+            green: None,
+            blue: None,
+            alpha: default_alpha(),
+            radius: None,
+            density: None,
+            friction: None,
+            restitution: None,
+            image: None,
+            group: None,
+            tags: vec![],
+            available: None,
+        }
+    }
+
+    /// `None` when the choice leaves all of red, green and blue out, so a colour can be
+    /// assigned from a palette. A missing channel next to ones that are given counts as 0.
+    pub fn color(&self) -> Option<Color> {
+        if self.red.is_none() && self.green.is_none() && self.blue.is_none() {
+            return None;
+        }
+        Some(Color::from_rgba(
+            self.red.unwrap_or(0),
+            self.green.unwrap_or(0),
+            self.blue.unwrap_or(0),
+            self.alpha,
+        ))
+    }
+}
+
+/// The formats choices can be read from and written to.
+pub const FILE_TYPES: [&str; 4] = ["json", "csv", "txt", "jsonl"];
+
+pub fn load_choices_from_file(path: String, file_type: &str) -> Result<Vec<Choice>> {
+    match file_type.to_lowercase().as_str() {
+        "json" => load_choices_from_json(path),
+        "csv" => load_choices_from_csv(path),
+        "txt" => load_choices_from_txt(path),
+        "jsonl" => load_choices_from_jsonl(path),
+        _ => bail!("choices must be json, csv, txt or jsonl"),
+    }
+}
+
+/// Guesses the format of a choices file from its extension.
+pub fn file_type_from_path(path: &str) -> Result<String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension {
+        Some(extension) if FILE_TYPES.contains(&extension.as_str()) => Ok(extension),
+        _ => bail!(
+            "can't tell the format of {}, it must end in .json, .csv, .txt or .jsonl",
+            path
+        ),
     }
 }
 
@@ -30,13 +114,43 @@ pub fn load_choices_from_json(path: String) -> Result<Vec<Choice>> {
     let mut choices_file = File::open(&path)?;
     let mut choices_json = String::new();
     choices_file.read_to_string(&mut choices_json)?;
-    let choices: Vec<Choice> = serde_json::from_str(&choices_json)?;
+    parse_choices_json(&choices_json)
+}
 
+pub fn load_choices_from_csv(path: String) -> Result<Vec<Choice>> {
+    parse_choices_csv(File::open(path)?)
+}
+
+/// One name per line. Blank lines are skipped.
+pub fn load_choices_from_txt(path: String) -> Result<Vec<Choice>> {
+    let choices = read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(Choice::new)
+        .collect();
     Ok(choices)
 }
 
-pub fn load_choices_from_csv(path: String) -> Result<Vec<Choice>> {
-    let mut reader = csv::Reader::from_path(path)?;
+/// One JSON choice per line. Blank lines are skipped.
+pub fn load_choices_from_jsonl(path: String) -> Result<Vec<Choice>> {
+    let mut choices = vec![];
+    for line in read_to_string(path)?.lines() {
+        if !line.trim().is_empty() {
+            choices.push(serde_json::from_str(line)?);
+        }
+    }
+    Ok(choices)
+}
+
+pub fn parse_choices_json(choices_json: &str) -> Result<Vec<Choice>> {
+    let choices: Vec<Choice> = serde_json::from_str(choices_json)?;
+    Ok(choices)
+}
+
+/// Reads choices from CSV with a header row naming the `Choice` fields.
+pub fn parse_choices_csv(csv: impl Read) -> Result<Vec<Choice>> {
+    let mut reader = csv::Reader::from_reader(csv);
     let mut choices = vec![];
     for result in reader.deserialize() {
         let choice: Choice = result?;
@@ -48,13 +162,321 @@ pub fn load_choices_from_csv(path: String) -> Result<Vec<Choice>> {
 pub fn load_choices_from_stdin() -> Result<Vec<Choice>> {
     let mut input = String::new();
     std::io::stdin().read_line(&mut input)?;
-    Ok(serde_json::from_str(&input)?)
+    parse_choices_json(&input)
+}
+
+/// Checks the per-choice physics settings before they reach the physics engine. A ball has to
+/// fit between the walls with room to spare, as that's where it is placed at random.
+pub fn validate_choices(choices: &[Choice], config: &Config) -> Result<()> {
+    let max_radius = (config.width - config.wall_width * 2.0) / 4.0;
+    for choice in choices {
+        if let Some(radius) = choice.radius {
+            if radius.is_nan() || radius <= 0.0 || radius >= max_radius {
+                bail!(
+                    "the radius of {} must be more than 0 and less than {}",
+                    choice.name,
+                    max_radius
+                );
+            }
+        }
+        if let Some(density) = choice.density {
+            if density.is_nan() || density <= 0.0 {
+                bail!("the density of {} must be more than 0", choice.name);
+            }
+        }
+        if let Some(friction) = choice.friction {
+            if friction.is_nan() || friction < 0.0 {
+                bail!("the friction of {} can't be negative", choice.name);
+            }
+        }
+        if let Some(restitution) = choice.restitution {
+            if restitution.is_nan() || restitution < 0.0 {
+                bail!("the restitution of {} can't be negative", choice.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Which choices get a ball after the config's filters have been applied.
+pub struct ChoiceFilter {
+    /// One flag per choice, in the same order.
+    pub keep: Vec<bool>,
+    /// Names to leave out, from `exclude` or the absent file, that match no choice. Usually a
+    /// typo worth telling the user about.
+    pub unknown_names: Vec<String>,
+}
+
+impl ChoiceFilter {
+    /// The choices that were kept, out of the same list that was filtered.
+    pub fn apply(&self, choices: &[Choice]) -> Vec<Choice> {
+        choices
+            .iter()
+            .zip(self.keep.iter())
+            .filter(|(_choice, keep)| **keep)
+            .map(|(choice, _keep)| choice.clone())
+            .collect()
+    }
+
+    pub fn left_out(&self) -> usize {
+        self.keep.iter().filter(|keep| !**keep).count()
+    }
+}
+
+/// Takes out unavailable choices and applies the tag, name and absent file filters from the
+/// config. Names match ignoring case.
+pub fn filter_choices(choices: &[Choice], config: &Config) -> Result<ChoiceFilter> {
+    let mut excluded_names = config.exclude.clone();
+    if let Some(absent_file) = &config.absent_file {
+        // The absent file is usually a plain list of names, whatever it's called
+        let file_type = file_type_from_path(absent_file).unwrap_or_else(|_| "txt".to_owned());
+        let absent = load_choices_from_file(absent_file.clone(), &file_type)?;
+        excluded_names.extend(absent.into_iter().map(|choice| choice.name));
+    }
+    let unknown_names = excluded_names
+        .iter()
+        .filter(|name| !choices.iter().any(|choice| same_name(&choice.name, name)))
+        .cloned()
+        .collect();
+
+    let has_tag =
+        |choice: &Choice, tags: &[String]| choice.tags.iter().any(|tag| tags.contains(tag));
+    let keep = choices
+        .iter()
+        .map(|choice| {
+            choice.available.unwrap_or(true)
+                && (config.include_tags.is_empty() || has_tag(choice, &config.include_tags))
+                && !has_tag(choice, &config.exclude_tags)
+                && !excluded_names
+                    .iter()
+                    .any(|name| same_name(&choice.name, name))
+        })
+        .collect();
+    Ok(ChoiceFilter {
+        keep,
+        unknown_names,
+    })
+}
+
+pub fn save_choices(path: &str, file_type: &str, choices: &[Choice]) -> Result<()> {
+    match file_type.to_lowercase().as_str() {
+        "json" => save_choices_to_json(path, choices),
+        "csv" => save_choices_to_csv(path, choices),
+        "txt" => save_choices_to_txt(path, choices),
+        "jsonl" => save_choices_to_jsonl(path, choices),
+        _ => bail!("choices must be json, csv, txt or jsonl"),
+    }
+}
+
+pub fn save_choices_to_json(path: &str, choices: &[Choice]) -> Result<()> {
+    let mut choices_file = File::create(path)?;
+    choices_file.write_all(serde_json::to_string_pretty(choices)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn save_choices_to_csv(path: &str, choices: &[Choice]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for choice in choices {
+        writer.serialize(CsvChoice::from(choice))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Only the names survive, txt has nowhere to keep colours or other fields.
+pub fn save_choices_to_txt(path: &str, choices: &[Choice]) -> Result<()> {
+    let mut choices_file = File::create(path)?;
+    for choice in choices {
+        writeln!(choices_file, "{}", choice.name)?;
+    }
+    Ok(())
+}
+
+pub fn save_choices_to_jsonl(path: &str, choices: &[Choice]) -> Result<()> {
+    let mut choices_file = File::create(path)?;
+    for choice in choices {
+        writeln!(choices_file, "{}", serde_json::to_string(choice)?)?;
+    }
+    Ok(())
+}
+
+/// A `Choice` as a CSV row. Every row needs every column, even empty ones, and the tags
+/// share a single column.
+#[derive(Serialize)]
+struct CsvChoice<'a> {
+    name: &'a str,
+    red: Option<u8>,
+    green: Option<u8>,
+    blue: Option<u8>,
+    alpha: u8,
+    radius: Option<f32>,
+    density: Option<f32>,
+    friction: Option<f32>,
+    restitution: Option<f32>,
+    image: Option<&'a str>,
+    group: Option<&'a str>,
+    tags: String,
+    available: Option<bool>,
 }
 
-fn default_color() -> u8 {
-    0
+impl<'a> From<&'a Choice> for CsvChoice<'a> {
+    fn from(choice: &'a Choice) -> Self {
+        Self {
+            name: &choice.name,
+            red: choice.red,
+            green: choice.green,
+            blue: choice.blue,
+            alpha: choice.alpha,
+            radius: choice.radius,
+            density: choice.density,
+            friction: choice.friction,
+            restitution: choice.restitution,
+            image: choice.image.as_deref(),
+            group: choice.group.as_deref(),
+            tags: choice.tags.join(";"),
+            available: choice.available,
+        }
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
 }
 
 fn default_alpha() -> u8 {
     255
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("choices_test_{}_{}", std::process::id(), name))
+    }
+
+    fn tagged(name: &str, tags: &[&str]) -> Choice {
+        let mut choice = Choice::new(name);
+        choice.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        choice
+    }
+
+    fn team() -> Vec<Choice> {
+        vec![
+            tagged("Alice", &["backend"]),
+            tagged("Bob", &["frontend"]),
+            tagged("Carol", &["backend", "lead"]),
+            tagged("Dave", &[]),
+        ]
+    }
+
+    fn kept_names(choices: &[Choice], config: &Config) -> Vec<String> {
+        filter_choices(choices, config)
+            .unwrap()
+            .apply(choices)
+            .into_iter()
+            .map(|choice| choice.name)
+            .collect()
+    }
+
+    #[test]
+    fn include_tags_keep_only_choices_with_one_of_them() {
+        let mut config = Config::default();
+        config.include_tags = vec!["backend".to_owned()];
+
+        assert_eq!(kept_names(&team(), &config), vec!["Alice", "Carol"]);
+    }
+
+    #[test]
+    fn exclude_tags_win_over_include_tags() {
+        let mut config = Config::default();
+        config.include_tags = vec!["backend".to_owned()];
+        config.exclude_tags = vec!["lead".to_owned()];
+
+        assert_eq!(kept_names(&team(), &config), vec!["Alice"]);
+    }
+
+    #[test]
+    fn unavailable_choices_are_left_out() {
+        let mut choices = team();
+        choices[1].available = Some(false);
+        choices[2].available = Some(true);
+
+        let filter = filter_choices(&choices, &Config::default()).unwrap();
+
+        assert_eq!(filter.keep, vec![true, false, true, true]);
+        assert_eq!(filter.left_out(), 1);
+    }
+
+    #[test]
+    fn excluded_names_ignore_case_and_unknown_ones_are_reported() {
+        let mut config = Config::default();
+        config.exclude = vec![" alice".to_owned(), "BOB".to_owned(), "Erin".to_owned()];
+
+        let filter = filter_choices(&team(), &config).unwrap();
+
+        assert_eq!(filter.keep, vec![false, false, true, true]);
+        assert_eq!(filter.unknown_names, vec!["Erin"]);
+    }
+
+    #[test]
+    fn names_in_the_absent_file_are_left_out() {
+        let absent_path = temp_path("absent.txt");
+        std::fs::write(&absent_path, "carol\n\nDave\n").unwrap();
+        let mut config = Config::default();
+        config.absent_file = Some(absent_path.to_str().unwrap().to_owned());
+
+        let kept = kept_names(&team(), &config);
+        std::fs::remove_file(absent_path).unwrap();
+
+        assert_eq!(kept, vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn a_missing_absent_file_is_an_error() {
+        let mut config = Config::default();
+        config.absent_file = Some(temp_path("missing.txt").to_str().unwrap().to_owned());
+
+        assert!(filter_choices(&team(), &config).is_err());
+    }
+
+    #[test]
+    fn choices_survive_a_round_trip_through_csv() {
+        let json = r#"[
+            {
+                "name": "Alice",
+                "red": 255,
+                "green": 0,
+                "blue": 10,
+                "alpha": 200,
+                "radius": 12.5,
+                "density": 2.0,
+                "friction": 0.3,
+                "restitution": 0.9,
+                "image": "alice.png",
+                "group": "Team A",
+                "tags": ["backend", "on call"],
+                "available": false
+            },
+            { "name": "Bob" }
+        ]"#;
+        let choices = parse_choices_json(json).unwrap();
+        let csv_path = temp_path("round_trip.csv");
+        let json_path = temp_path("round_trip.json");
+
+        save_choices_to_csv(csv_path.to_str().unwrap(), &choices).unwrap();
+        let from_csv = load_choices_from_csv(csv_path.to_str().unwrap().to_owned()).unwrap();
+        save_choices_to_json(json_path.to_str().unwrap(), &from_csv).unwrap();
+        let written: Value = serde_json::from_str(&read_to_string(&json_path).unwrap()).unwrap();
+        std::fs::remove_file(csv_path).unwrap();
+        std::fs::remove_file(json_path).unwrap();
+
+        assert_eq!(written, serde_json::to_value(&choices).unwrap());
+        assert_eq!(written[0]["tags"], json!(["backend", "on call"]));
+        assert_eq!(written[1], json!({ "name": "Bob", "alpha": 255 }));
+    }
+}
@@ -0,0 +1,85 @@
+use eyre::{bail, Result};
+
+use crate::choices::{validate_choices, Choice};
+use crate::config::config_struct::Config;
+use crate::event_manager::event::EventKind;
+use crate::event_manager::EventManager;
+use crate::simulation::{Outcome, Simulation};
+use crate::winner_hooks::WinnerHooks;
+use crate::MainState;
+
+/// Runs a drop from Rust without going through the command line.
+///
+/// ```no_run
+/// use random_chooser_drop::{Choice, Chooser};
+/// use random_chooser_drop::config::config_struct::Config;
+///
+/// let choices = vec![Choice::new("Alice"), Choice::new("Bob")];
+/// let outcome = Chooser::new(Config::default(), choices).seed(42).run_headless()?;
+/// println!("{} won", outcome.winner);
+/// # Ok::<(), eyre::Report>(())
+/// ```
+pub struct Chooser {
+    config: Config,
+    choices: Vec<Choice>,
+    drop_at_step: u64,
+}
+
+impl Chooser {
+    pub fn new(config: Config, choices: Vec<Choice>) -> Self {
+        Self {
+            config,
+            choices,
+            drop_at_step: 0,
+        }
+    }
+
+    /// The same seed with the same config and choices always picks the same winner.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// How many steps to let the balls settle before removing the floor when running headless.
+    pub fn drop_at_step(mut self, drop_at_step: u64) -> Self {
+        self.drop_at_step = drop_at_step;
+        self
+    }
+
+    /// Simulates the whole drop without a window and returns the winner, running the winner
+    /// hooks from the config before returning.
+    pub fn run_headless(self) -> Result<Outcome> {
+        if self.choices.is_empty() {
+            bail!("there must be at least one choice to drop");
+        }
+        validate_choices(&self.choices, &self.config)?;
+
+        let mut event_manager = EventManager::new();
+        let events = event_manager.subscribe(EventKind::Intersection);
+        let mut simulation = Simulation::new(&self.config, &mut event_manager);
+        simulation.setup(&self.choices);
+        let outcome = simulation.run_headless(&mut event_manager, &events, self.drop_at_step)?;
+        if let Some(winner_hooks) = WinnerHooks::new(&self.config) {
+            let winner = simulation
+                .winner()
+                .and_then(|id| simulation.choice_index(id))
+                .map(|index| &self.choices[index]);
+            if let Some(winner) = winner {
+                winner_hooks.notify(winner, 1);
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// The windowed drop, for registering observers or recording before calling
+    /// `MainState::run`.
+    pub fn into_main_state(self) -> MainState {
+        MainState::with_choices(self.config, self.choices)
+    }
+
+    /// Opens a window and runs the drop until the window is closed. Returns `None` if it was
+    /// closed before a winner was decided.
+    pub fn run_windowed(self) -> Result<Option<Outcome>> {
+        self.into_main_state().run()
+    }
+}
@@ -1,17 +1,34 @@
 use ggez::graphics::Color;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::helpers::is_dark_color::is_dark_color;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     pub title: String,
     pub width: f32,
     pub height: f32,
+    #[serde(default)]
+    pub window_width: Option<f32>,
+    #[serde(default)]
+    pub window_height: Option<f32>,
     pub vsync: bool,
+    /// A built-in theme or a theme file. Its colours are filled in by `load_config`.
+    #[serde(default)]
+    pub theme: Option<String>,
     #[serde(with = "crate::helpers::serde_color")]
     pub background_color: Color,
     pub gravity: f32,
+    #[serde(default = "default_steps_per_second")]
+    pub steps_per_second: u32,
+    #[serde(default)]
+    pub seed: Option<u64>,
     pub choice_radius: f32,
     pub bounciness: f32,
+    #[serde(default = "default_choice_density")]
+    pub choice_density: f32,
+    #[serde(default = "default_choice_friction")]
+    pub choice_friction: f32,
     pub floor_position_y: f32,
     pub floor_height: f32,
     #[serde(with = "crate::helpers::serde_color")]
@@ -31,11 +48,264 @@ pub struct Config {
     pub winning_background_color_light: Color,
     #[serde(with = "crate::helpers::serde_color")]
     pub winning_background_color_dark: Color,
-    pub winning_background_color_alpha: f32,
+    /// Overrides the alpha of both winning background colours when set.
+    #[serde(default)]
+    pub winning_background_color_alpha: Option<f32>,
+    #[serde(default = "default_winner_message")]
+    pub winner_message: String,
+    #[serde(default)]
+    pub winner_font: Option<String>,
+    #[serde(default = "default_winner_font_size")]
+    pub winner_font_size: f32,
+    /// Centre of the winner message as a fraction of the window size.
+    #[serde(default = "default_winner_position")]
+    pub winner_position: [f32; 2],
+    #[serde(default)]
+    pub winner_reveal: WinnerReveal,
+    #[serde(default = "default_winner_reveal_seconds")]
+    pub winner_reveal_seconds: f32,
+    #[serde(default = "default_idle_velocity_threshold")]
+    pub idle_velocity_threshold: f32,
+    #[serde(default = "default_idle_seconds")]
+    pub idle_seconds: f32,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: f32,
+    #[serde(default)]
+    pub stuck_action: StuckAction,
+    #[serde(default = "default_nudge_speed")]
+    pub nudge_speed: f32,
+    #[serde(default = "default_max_nudges")]
+    pub max_nudges: u32,
+    #[serde(default)]
+    pub camera_follow: bool,
+    #[serde(default = "default_camera_finish_zoom")]
+    pub camera_finish_zoom: f32,
+    #[serde(default = "default_camera_finish_zone")]
+    pub camera_finish_zone: f32,
+    #[serde(default = "default_camera_smoothing")]
+    pub camera_smoothing: f32,
+    #[serde(default)]
+    pub label_mode: LabelMode,
+    #[serde(default = "default_label_font_size")]
+    pub label_font_size: f32,
+    #[serde(default)]
+    pub show_leaderboard: bool,
+    #[serde(default = "default_leaderboard_width")]
+    pub leaderboard_width: f32,
+    #[serde(default = "default_true")]
+    pub trails: bool,
+    #[serde(default = "default_trail_length")]
+    pub trail_length: usize,
+    #[serde(default)]
+    pub collision_sparks: bool,
+    #[serde(default = "default_true")]
+    pub confetti: bool,
+    #[serde(default = "default_confetti_particles")]
+    pub confetti_particles: usize,
+    #[serde(default)]
+    pub plink_sound: Option<String>,
+    #[serde(default)]
+    pub drumroll_sound: Option<String>,
+    #[serde(default)]
+    pub fanfare_sound: Option<String>,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    #[serde(default = "default_plink_min_interval")]
+    pub plink_min_interval: f32,
+    #[serde(default)]
+    pub mute: bool,
+    /// Patterns inside the balls, labels on every ball, colour-blind-safe automatic colours
+    /// and the winner announced on stdout.
+    #[serde(default)]
+    pub accessibility: bool,
+    /// Counts down for this long after a start trigger before removing the floor.
+    #[serde(default)]
+    pub countdown_seconds: f32,
+    /// Start the countdown as soon as the drop opens instead of waiting for a start trigger.
+    #[serde(default)]
+    pub auto_start: bool,
+    /// A shell command to run once the winner is declared, see `WinnerHooks`.
+    #[serde(default)]
+    pub on_winner: Option<String>,
+    /// A URL the winner is POSTed to as JSON once the winner is declared.
+    #[serde(default)]
+    pub winner_webhook: Option<String>,
+    /// Starts the localhost control server on this port, with its WebSocket on the next port.
+    #[serde(default)]
+    pub control_port: Option<u16>,
+    /// Only drop choices with at least one of these tags. Empty means every choice.
+    #[serde(default)]
+    pub include_tags: Vec<String>,
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// Names of choices to leave out.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// A choices file, or a text file with one name per line, of choices to leave out.
+    #[serde(default)]
+    pub absent_file: Option<String>,
     #[serde(default = "default_use_stdin")]
     pub use_stdin: bool,
 }
 
+/// The config.json shipped with the chooser, for embedding without a config file.
+impl Default for Config {
+    fn default() -> Self {
+        super::parse_config(include_str!("../../config.json"), None).unwrap()
+    }
+}
+
+impl Config {
+    /// The window can be smaller or larger than the board, defaulting to the board size.
+    pub fn window_size(&self) -> (f32, f32) {
+        (
+            self.window_width.unwrap_or(self.width),
+            self.window_height.unwrap_or(self.height),
+        )
+    }
+
+    pub fn countdown_steps(&self) -> u64 {
+        (self.countdown_seconds * self.steps_per_second as f32).round() as u64
+    }
+
+    /// The overlay behind the winner message, picked to contrast with the winner's colour.
+    pub fn winning_background_color(&self, winner_color: &Color) -> Color {
+        let mut background_color = if is_dark_color(winner_color) {
+            self.winning_background_color_light
+        } else {
+            self.winning_background_color_dark
+        };
+        if let Some(alpha) = self.winning_background_color_alpha {
+            background_color.a = alpha;
+        }
+        background_color
+    }
+}
+
+/// What to do when every ball has stopped moving before one of them reached the finish.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StuckAction {
+    /// Knock the balls loose, falling back to a tiebreak after `max_nudges` attempts.
+    #[default]
+    Nudge,
+    /// Declare the ball closest to the finish the winner.
+    Tiebreak,
+}
+
+/// Which balls get their name drawn next to them while they fall.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelMode {
+    #[default]
+    Off,
+    Always,
+    /// Only the ball closest to the finish.
+    Leader,
+    /// Only the ball under the mouse.
+    Hover,
+}
+
+/// How the winner message appears once a ball has won.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WinnerReveal {
+    #[default]
+    None,
+    /// Grows from nothing to full size.
+    Scale,
+    /// Types out one character at a time.
+    Typewriter,
+}
+
 fn default_use_stdin() -> bool {
     false
 }
+
+fn default_steps_per_second() -> u32 {
+    60
+}
+
+fn default_choice_density() -> f32 {
+    1.0
+}
+
+fn default_choice_friction() -> f32 {
+    0.5
+}
+
+fn default_idle_velocity_threshold() -> f32 {
+    1.0
+}
+
+fn default_idle_seconds() -> f32 {
+    5.0
+}
+
+fn default_timeout_seconds() -> f32 {
+    120.0
+}
+
+fn default_nudge_speed() -> f32 {
+    50.0
+}
+
+fn default_max_nudges() -> u32 {
+    3
+}
+
+fn default_camera_finish_zoom() -> f32 {
+    2.0
+}
+
+fn default_camera_finish_zone() -> f32 {
+    0.2
+}
+
+fn default_camera_smoothing() -> f32 {
+    0.1
+}
+
+fn default_label_font_size() -> f32 {
+    16.0
+}
+
+fn default_leaderboard_width() -> f32 {
+    300.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_trail_length() -> usize {
+    15
+}
+
+fn default_confetti_particles() -> usize {
+    200
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+fn default_plink_min_interval() -> f32 {
+    0.05
+}
+
+fn default_winner_message() -> String {
+    "{name} Won!!!".to_owned()
+}
+
+fn default_winner_font_size() -> f32 {
+    72.0
+}
+
+fn default_winner_position() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+fn default_winner_reveal_seconds() -> f32 {
+    1.0
+}
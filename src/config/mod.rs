@@ -1,15 +1,42 @@
 use std::fs::File;
 use std::io::Read;
 
-use eyre::Result;
+use eyre::{bail, Result};
+use serde_json::Value;
 
 use self::config_struct::Config;
+use self::theme::{load_theme, DEFAULT_THEME};
 pub mod config_struct;
+pub mod theme;
 
-pub fn load_config(path: String) -> Result<Config> {
+/// Loads the config with the colours of its theme filled in. `theme` replaces the theme named
+/// in the config file, and colours set directly in the config file win over either.
+pub fn load_config(path: String, theme: Option<String>) -> Result<Config> {
     let mut config_file = File::open(path)?;
     let mut config_json = String::new();
     config_file.read_to_string(&mut config_json)?;
-    let config: Config = serde_json::from_str(&config_json)?;
+    parse_config(&config_json, theme)
+}
+
+/// `load_config` for a config that is already in memory.
+pub fn parse_config(config_json: &str, theme: Option<String>) -> Result<Config> {
+    let mut config_values = match serde_json::from_str(config_json)? {
+        Value::Object(config_values) => config_values,
+        _ => bail!("the config must be a JSON object"),
+    };
+
+    let theme = theme
+        .or_else(|| {
+            config_values
+                .get("theme")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+        })
+        .unwrap_or_else(|| DEFAULT_THEME.to_owned());
+    let mut values = load_theme(&theme)?;
+    config_values.insert("theme".to_owned(), Value::String(theme));
+    values.extend(config_values);
+
+    let config: Config = serde_json::from_value(Value::Object(values))?;
     Ok(config)
 }
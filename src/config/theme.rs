@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use eyre::{bail, Result};
+use serde_json::{json, Map, Value};
+
+/// Used when neither the config nor the command line picks a theme.
+pub const DEFAULT_THEME: &str = "classic";
+
+/// The colours every theme starts from, so presets and theme files only need to list the
+/// colours they change.
+fn classic() -> Value {
+    json!({
+        "background_color": [100, 100, 100],
+        "floor_color": [0, 0, 0],
+        "nail_color": [10, 10, 10],
+        "wall_color": [0, 0, 0],
+        "winning_background_color_light": [255, 255, 255, 51],
+        "winning_background_color_dark": [0, 0, 0, 51],
+    })
+}
+
+fn preset(name: &str) -> Option<Value> {
+    let preset = match name {
+        "classic" => classic(),
+        "dark" => json!({
+            "background_color": [24, 24, 28],
+            "floor_color": [90, 90, 100],
+            "nail_color": [170, 170, 180],
+            "wall_color": [70, 70, 80],
+            "winning_background_color_light": [255, 255, 255, 40],
+            "winning_background_color_dark": [0, 0, 0, 160],
+        }),
+        "light" => json!({
+            "background_color": [245, 245, 240],
+            "floor_color": [60, 60, 60],
+            "nail_color": [40, 40, 40],
+            "wall_color": [120, 120, 120],
+            "winning_background_color_light": [255, 255, 255, 160],
+            "winning_background_color_dark": [0, 0, 0, 60],
+        }),
+        "high-contrast" => json!({
+            "background_color": [0, 0, 0],
+            "floor_color": [255, 255, 255],
+            "nail_color": [255, 255, 0],
+            "wall_color": [255, 255, 255],
+            "winning_background_color_light": [255, 255, 255, 220],
+            "winning_background_color_dark": [0, 0, 0, 220],
+        }),
+        // Drawn from the same Okabe-Ito colours as the palette in `helpers::palette`
+        "colour-blind-safe" => json!({
+            "background_color": [240, 240, 240],
+            "floor_color": [0, 114, 178],
+            "nail_color": [0, 0, 0],
+            "wall_color": [0, 114, 178],
+            "winning_background_color_light": [255, 255, 255, 170],
+            "winning_background_color_dark": [0, 0, 0, 120],
+        }),
+        "holiday" => json!({
+            "background_color": [16, 70, 40],
+            "floor_color": [180, 20, 30],
+            "nail_color": [230, 190, 60],
+            "wall_color": [180, 20, 30],
+            "winning_background_color_light": [255, 250, 240, 80],
+            "winning_background_color_dark": [10, 30, 20, 140],
+        }),
+        _ => return None,
+    };
+    Some(preset)
+}
+
+/// The colours of a built-in preset or of a JSON theme file, layered over the classic colours.
+/// Theme files may only set the colours that `classic` sets.
+pub fn load_theme(name_or_path: &str) -> Result<Map<String, Value>> {
+    let theme = if let Some(preset) = preset(name_or_path) {
+        preset
+    } else if Path::new(name_or_path).is_file() {
+        let mut theme_file = File::open(name_or_path)?;
+        let mut theme_json = String::new();
+        theme_file.read_to_string(&mut theme_json)?;
+        serde_json::from_str(&theme_json)?
+    } else {
+        bail!(
+            "unknown theme {}, use classic, dark, light, high-contrast, colour-blind-safe, holiday or a path to a theme file",
+            name_or_path
+        );
+    };
+
+    let mut colors = as_object(classic());
+    let theme = match theme {
+        Value::Object(theme) => theme,
+        _ => bail!("theme {} must be a JSON object", name_or_path),
+    };
+    // Only colours, so a shared theme file can't change anything else, like `on_winner`
+    for key in theme.keys() {
+        if !colors.contains_key(key) {
+            bail!(
+                "theme {} sets {}, themes can only set {}",
+                name_or_path,
+                key,
+                colors.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+    colors.extend(theme);
+    Ok(colors)
+}
+
+fn as_object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
@@ -9,6 +9,8 @@ pub struct DrawData {
     rectangles: HashMap<u128, Rect>,
     rotations: HashMap<u128, f32>,
     names: HashMap<u128, String>,
+    radii: HashMap<u128, f32>,
+    images: HashMap<u128, String>,
 }
 
 impl DrawData {
@@ -36,6 +38,14 @@ impl DrawData {
         self.names.insert(id, name);
     }
 
+    pub fn insert_radius(&mut self, id: u128, radius: f32) {
+        self.radii.insert(id, radius);
+    }
+
+    pub fn insert_image(&mut self, id: u128, path: String) {
+        self.images.insert(id, path);
+    }
+
     pub fn get_color(&mut self, id: u128) -> Color {
         *self.colors.get(&id).unwrap()
     }
@@ -59,6 +69,14 @@ impl DrawData {
     pub fn get_name(&self, id: u128) -> Option<&String> {
         self.names.get(&id)
     }
+
+    pub fn get_radius(&self, id: u128) -> f32 {
+        *self.radii.get(&id).unwrap()
+    }
+
+    pub fn get_image(&self, id: u128) -> Option<&String> {
+        self.images.get(&id)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,302 @@
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+
+use crate::choices::Choice;
+use crate::config::config_struct::Config;
+use crate::helpers::palette::{default_choice_color, palette_color, palette_len};
+use crate::panel::{self, PADDING, ROW_HEIGHT, SWATCH_SIZE};
+
+const WIDTH: f32 = 420.0;
+const HELP: [&str; 3] = [
+    "Enter add  Delete remove  F2 on/off",
+    "F3 next colour, or type #rrggbb first",
+    "Ctrl+S save  Tab close",
+];
+
+/// What `MainState` needs to do after the editor handled a key.
+pub enum EditorAction {
+    None,
+    /// The editor was closed after the choices changed, so the balls need re-spawning.
+    Apply,
+    Save,
+}
+
+/// An overlay for editing the choices without touching the choices file. Choices switched
+/// off stay in the list, and in the saved file, but don't get a ball this round.
+pub struct Editor {
+    pub open: bool,
+    entries: Vec<Entry>,
+    input: String,
+    selected: usize,
+    next_color: usize,
+    changed: bool,
+    status: Option<String>,
+}
+
+struct Entry {
+    choice: Choice,
+    enabled: bool,
+}
+
+impl Editor {
+    pub fn new(choices: &[Choice]) -> Self {
+        let mut editor = Self {
+            open: false,
+            entries: vec![],
+            input: String::new(),
+            selected: 0,
+            next_color: 0,
+            changed: false,
+            status: None,
+        };
+        editor.set_choices(choices);
+        editor
+    }
+
+    /// Replaces every entry, for when the choices were changed from somewhere else.
+    pub fn set_choices(&mut self, choices: &[Choice]) {
+        self.entries = choices
+            .iter()
+            .map(|choice| Entry {
+                choice: choice.clone(),
+                enabled: true,
+            })
+            .collect();
+        self.next_color = choices.len();
+        self.selected = 0;
+        self.changed = false;
+    }
+
+    /// Switches entries on or off, one flag per entry in order, for choices filtered out
+    /// when they were loaded.
+    pub fn set_enabled(&mut self, enabled: &[bool]) {
+        for (entry, enabled) in self.entries.iter_mut().zip(enabled) {
+            entry.enabled = *enabled;
+        }
+    }
+
+    pub fn toggle(&mut self) -> EditorAction {
+        self.open = !self.open;
+        self.status = None;
+        if !self.open && self.changed {
+            self.changed = false;
+            return EditorAction::Apply;
+        }
+        EditorAction::None
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, ctrl: bool, accessible: bool) -> EditorAction {
+        match keycode {
+            KeyCode::Tab => return self.toggle(),
+            KeyCode::S if ctrl => return EditorAction::Save,
+            KeyCode::Return => {
+                let name = self.input.trim().to_owned();
+                if !name.is_empty() {
+                    self.entries.push(Entry {
+                        choice: Choice::new(name),
+                        enabled: true,
+                    });
+                    self.selected = self.entries.len() - 1;
+                    self.changed = true;
+                }
+                self.input.clear();
+            }
+            KeyCode::Back => {
+                self.input.pop();
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.entries.len() => self.selected += 1,
+            KeyCode::Delete if self.selected < self.entries.len() => {
+                self.entries.remove(self.selected);
+                self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+                self.changed = true;
+            }
+            KeyCode::F2 => {
+                if let Some(entry) = self.entries.get_mut(self.selected) {
+                    entry.enabled = !entry.enabled;
+                    self.changed = true;
+                }
+            }
+            KeyCode::F3 => self.set_color(accessible),
+            _ => {}
+        }
+        EditorAction::None
+    }
+
+    /// Gives the selected entry the hex colour typed into the input, or the next colour from the
+    /// palette when nothing was typed.
+    fn set_color(&mut self, accessible: bool) {
+        if self.selected >= self.entries.len() {
+            return;
+        }
+        let input = self.input.trim();
+        let (red, green, blue) = if input.is_empty() {
+            self.next_color += 1;
+            palette_color(self.next_color - 1, accessible).to_rgb()
+        } else if let Some(color) = parse_hex_color(input) {
+            self.input.clear();
+            color
+        } else {
+            self.status = Some(format!("{} isn't a colour like #ff8800", input));
+            return;
+        };
+
+        let choice = &mut self.entries[self.selected].choice;
+        choice.red = Some(red);
+        choice.green = Some(green);
+        choice.blue = Some(blue);
+        self.changed = true;
+    }
+
+    pub fn type_character(&mut self, character: char) {
+        if !character.is_control() {
+            self.input.push(character);
+        }
+    }
+
+    /// The choices that should get a ball this round.
+    pub fn enabled_choices(&self) -> Vec<Choice> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.choice.clone())
+            .collect()
+    }
+
+    /// Every choice, including the ones switched off, for saving.
+    pub fn all_choices(&self) -> Vec<Choice> {
+        self.entries
+            .iter()
+            .map(|entry| entry.choice.clone())
+            .collect()
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    /// A panel on the left of the window, drawn in screen coordinates.
+    pub fn draw(&self, context: &mut Context, config: &Config) -> GameResult {
+        let (_window_width, window_height) = graphics::drawable_size(context);
+        let text_color = panel::draw_background(context, config, 0.0, WIDTH, 0.9)?;
+
+        let mut y = PADDING;
+        panel::draw_text(context, "Choices", PADDING, y, text_color)?;
+        y += ROW_HEIGHT;
+        panel::draw_text(
+            context,
+            &format!("> {}_", self.input),
+            PADDING,
+            y,
+            text_color,
+        )?;
+        y += ROW_HEIGHT;
+        self.draw_palette(context, y, text_color, config.accessibility)?;
+        y += ROW_HEIGHT * 1.5;
+
+        let mut footer_rows = HELP.len() as f32;
+        if self.status.is_some() {
+            footer_rows += 1.0;
+        }
+        let max_rows =
+            ((window_height - y - PADDING - ROW_HEIGHT * footer_rows) / ROW_HEIGHT) as usize;
+        let first_row = (self.selected + 1).saturating_sub(max_rows);
+        for (index, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first_row)
+            .take(max_rows)
+        {
+            if index == self.selected {
+                let highlight = MeshBuilder::new()
+                    .rectangle(
+                        DrawMode::stroke(1.0),
+                        Rect::new(PADDING / 2.0, y, WIDTH - PADDING, ROW_HEIGHT),
+                        text_color,
+                    )
+                    .build(context)?;
+                graphics::draw(context, &highlight, DrawParam::new())?;
+            }
+            let color = entry
+                .choice
+                .color()
+                .unwrap_or_else(|| default_choice_color(index, config.accessibility));
+            let marker = if entry.enabled { "[x]" } else { "[ ]" };
+            let mut row_color = text_color;
+            if !entry.enabled {
+                row_color.a = 0.5;
+            }
+            panel::draw_row(
+                context,
+                Some(color),
+                &format!("{} {}", marker, entry.choice.name),
+                PADDING,
+                y,
+                row_color,
+            )?;
+            y += ROW_HEIGHT;
+        }
+
+        let mut footer_y = window_height - PADDING - ROW_HEIGHT * footer_rows;
+        if let Some(status) = &self.status {
+            panel::draw_text(context, status, PADDING, footer_y, text_color)?;
+            footer_y += ROW_HEIGHT;
+        }
+        for line in HELP.iter() {
+            panel::draw_text(context, line, PADDING, footer_y, text_color)?;
+            footer_y += ROW_HEIGHT;
+        }
+        Ok(())
+    }
+    /// The colours F3 hands out, with the next one outlined.
+    fn draw_palette(
+        &self,
+        context: &mut Context,
+        y: f32,
+        text_color: Color,
+        accessible: bool,
+    ) -> GameResult {
+        let colors = palette_len(accessible);
+        for index in 0..colors {
+            let x = PADDING + index as f32 * (SWATCH_SIZE + PADDING);
+            panel::draw_swatch(context, palette_color(index, accessible), x, y)?;
+            if index == self.next_color % colors {
+                let outline = MeshBuilder::new()
+                    .rectangle(
+                        DrawMode::stroke(2.0),
+                        Rect::new(x - 3.0, y - 1.0, SWATCH_SIZE + 6.0, SWATCH_SIZE + 6.0),
+                        text_color,
+                    )
+                    .build(context)?;
+                graphics::draw(context, &outline, DrawParam::new())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `#rrggbb`, with or without the `#`.
+fn parse_hex_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |start: usize| u8::from_str_radix(&hex[start..start + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#ff8800"), Some((255, 136, 0)));
+        assert_eq!(parse_hex_color("00A0fF"), Some((0, 160, 255)));
+        assert_eq!(parse_hex_color("#ff880"), None);
+        assert_eq!(parse_hex_color("orange"), None);
+        assert_eq!(parse_hex_color("#+f8800"), None);
+    }
+}
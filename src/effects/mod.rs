@@ -0,0 +1,228 @@
+use std::collections::{HashMap, VecDeque};
+
+use crossbeam::channel::Receiver;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder};
+use ggez::{Context, GameResult};
+use rand::{thread_rng, Rng};
+
+use crate::config::config_struct::Config;
+use crate::draw_data::DataType;
+use crate::event_manager::event::{Event, EventKind};
+use crate::event_manager::EventManager;
+use crate::simulation::Simulation;
+
+const SPARK_SECONDS: f32 = 0.25;
+const SPARK_RADIUS: f32 = 3.0;
+const CONFETTI_SECONDS: f32 = 4.0;
+const CONFETTI_SIZE: f32 = 8.0;
+const CONFETTI_GRAVITY: f32 = 300.0;
+
+/// Purely visual extras layered on top of the board. Nothing in here feeds back into the
+/// simulation, so effects use their own unseeded randomness.
+pub struct Effects {
+    trails: HashMap<u128, VecDeque<(f32, f32)>>,
+    sparks: Vec<Spark>,
+    confetti: Vec<Confetti>,
+    events: Receiver<Event>,
+    trails_enabled: bool,
+    trail_length: usize,
+    confetti_enabled: bool,
+    confetti_particles: usize,
+}
+
+struct Spark {
+    x: f32,
+    y: f32,
+    age: f32,
+}
+
+struct Confetti {
+    x: f32,
+    y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    rotation: f32,
+    spin: f32,
+    color: Color,
+    age: f32,
+}
+
+impl Effects {
+    pub fn new(config: &Config, event_manager: &mut EventManager) -> Self {
+        let events = if config.collision_sparks {
+            event_manager.subscribe_many(&[EventKind::RoundReset, EventKind::Contact])
+        } else {
+            event_manager.subscribe(EventKind::RoundReset)
+        };
+
+        Self {
+            trails: HashMap::new(),
+            sparks: vec![],
+            confetti: vec![],
+            events,
+            trails_enabled: config.trails,
+            trail_length: config.trail_length,
+            confetti_enabled: config.confetti,
+            confetti_particles: config.confetti_particles,
+        }
+    }
+
+    /// Advances every effect by one simulation step.
+    pub fn update(&mut self, simulation: &Simulation, dt: f32) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::RoundReset => self.clear(),
+                Event::Contact(collider_handle1, collider_handle2, _impact_speed) => {
+                    for collider_handle in [collider_handle1, collider_handle2].iter() {
+                        let physics = &simulation.physics;
+                        let id = match physics.get_id_by_collider_handle(*collider_handle) {
+                            Some(id) => id,
+                            None => continue,
+                        };
+                        if let DataType::Nail = simulation.draw_data.get_type(id) {
+                            let (x, y) = physics
+                                .get_position_by_collider_handle(*collider_handle)
+                                .unwrap();
+                            self.sparks.push(Spark { x, y, age: 0.0 });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self.trails_enabled {
+            let ball_tracker = &simulation.physics.ball_tracker;
+            for id in ball_tracker.ids() {
+                let ball = ball_tracker.get(id).unwrap();
+                let trail = self.trails.entry(id).or_default();
+                trail.push_back((ball.x, ball.y));
+                while trail.len() > self.trail_length {
+                    trail.pop_front();
+                }
+            }
+        }
+
+        for spark in self.sparks.iter_mut() {
+            spark.age += dt;
+        }
+        self.sparks.retain(|spark| spark.age < SPARK_SECONDS);
+
+        for confetti in self.confetti.iter_mut() {
+            confetti.velocity_y += CONFETTI_GRAVITY * dt;
+            confetti.x += confetti.velocity_x * dt;
+            confetti.y += confetti.velocity_y * dt;
+            confetti.rotation += confetti.spin * dt;
+            confetti.age += dt;
+        }
+        self.confetti
+            .retain(|confetti| confetti.age < CONFETTI_SECONDS);
+    }
+
+    /// Bursts confetti in the winner's colour from a point in screen coordinates.
+    pub fn start_confetti(&mut self, x: f32, y: f32, color: Color) {
+        if !self.confetti_enabled {
+            return;
+        }
+
+        let mut rng = thread_rng();
+        for _ in 0..self.confetti_particles {
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::PI * 2.0);
+            let speed: f32 = rng.gen_range(100.0..600.0);
+            let shade: f32 = rng.gen_range(0.6..1.2);
+            self.confetti.push(Confetti {
+                x,
+                y,
+                velocity_x: angle.cos() * speed,
+                velocity_y: angle.sin() * speed - 300.0,
+                rotation: 0.0,
+                spin: rng.gen_range(-10.0..10.0),
+                color: Color::new(
+                    (color.r * shade).min(1.0),
+                    (color.g * shade).min(1.0),
+                    (color.b * shade).min(1.0),
+                    1.0,
+                ),
+                age: 0.0,
+            });
+        }
+    }
+
+    /// Drops the events since the last update instead of showing them, for when playback jumps
+    /// to another step. Trails are cleared too so they don't streak across the jump.
+    pub fn skip(&mut self) {
+        while self.events.try_recv().is_ok() {}
+        self.clear();
+    }
+
+    /// Forgets every effect, for when the board is rebuilt and ball ids are handed out again.
+    pub fn clear(&mut self) {
+        self.trails.clear();
+        self.sparks.clear();
+        self.confetti.clear();
+    }
+
+    /// Fading trails behind each ball, added before the balls so they are drawn underneath.
+    pub fn draw_trails(&self, mesh_builder: &mut MeshBuilder, simulation: &mut Simulation) {
+        for (id, trail) in self.trails.iter() {
+            if !matches!(simulation.draw_data.get_type(*id), DataType::Ball) {
+                continue;
+            }
+            let mut color = simulation.draw_data.get_color(*id);
+            let radius = simulation.draw_data.get_radius(*id);
+            for (index, (x, y)) in trail.iter().enumerate() {
+                let strength = (index + 1) as f32 / (trail.len() + 1) as f32;
+                color.a = strength * 0.5;
+                mesh_builder.circle(DrawMode::fill(), [*x, *y], radius * strength, 0.5, color);
+            }
+        }
+    }
+
+    /// Brief flashes on the nails that were just hit.
+    pub fn draw_sparks(&self, mesh_builder: &mut MeshBuilder) {
+        for spark in self.sparks.iter() {
+            let progress = spark.age / SPARK_SECONDS;
+            let color = Color::new(1.0, 1.0, 0.6, 1.0 - progress);
+            mesh_builder.circle(
+                DrawMode::fill(),
+                [spark.x, spark.y],
+                SPARK_RADIUS * (1.0 + progress * 2.0),
+                0.5,
+                color,
+            );
+        }
+    }
+
+    /// Confetti lives in screen coordinates so it bursts over the winner banner.
+    pub fn draw_confetti(&self, context: &mut Context) -> GameResult {
+        if self.confetti.is_empty() {
+            return Ok(());
+        }
+
+        let mut mesh_builder = MeshBuilder::new();
+        for confetti in self.confetti.iter() {
+            let mut color = confetti.color;
+            color.a = 1.0 - confetti.age / CONFETTI_SECONDS;
+            let (sin, cos) = confetti.rotation.sin_cos();
+            let half_width = CONFETTI_SIZE / 2.0;
+            let half_height = CONFETTI_SIZE / 4.0;
+            let corners: Vec<[f32; 2]> = [
+                (-half_width, -half_height),
+                (half_width, -half_height),
+                (half_width, half_height),
+                (-half_width, half_height),
+            ]
+            .iter()
+            .map(|(x, y)| {
+                [
+                    confetti.x + x * cos - y * sin,
+                    confetti.y + x * sin + y * cos,
+                ]
+            })
+            .collect();
+            mesh_builder.polygon(DrawMode::fill(), &corners, color)?;
+        }
+        let mesh = mesh_builder.build(context)?;
+        graphics::draw(context, &mesh, DrawParam::new())
+    }
+}
@@ -1,8 +1,29 @@
-use ggez::event::KeyCode;
+use ggez::event::{KeyCode, MouseButton};
 use rapier2d::geometry::ColliderHandle;
 
-#[derive(Debug, strum_macros::ToString, strum_macros::AsRefStr, Clone, Copy)]
+/// Everything that goes over the event bus. Subscribers pick the events they want by
+/// `EventKind`, which has one variant per variant here.
+#[derive(Debug, strum_macros::EnumDiscriminants, Clone, Copy)]
+#[strum_discriminants(name(EventKind), derive(Hash))]
 pub enum Event {
     KeyPressed(KeyCode),
-    IntersectionEvent(ColliderHandle, ColliderHandle),
+    TextInput(char),
+    MouseClicked(MouseButton),
+    /// Any gamepad button, they all start the drop.
+    GamepadButtonPressed,
+    /// Start the drop from somewhere other than the keyboard, mouse or gamepad.
+    StartRequested,
+    Intersection(ColliderHandle, ColliderHandle),
+    /// Two colliders started touching. The last field is the speed of the fastest ball in the
+    /// contact just before it hit.
+    Contact(ColliderHandle, ColliderHandle, f32),
+    /// A ball crossed the finish, with its 1-based rank.
+    BallFinished(u128, usize),
+    /// The step the floor was removed at.
+    FloorRemoved(u64),
+    /// The board was rebuilt from scratch, for example when scrubbing back through a replay.
+    RoundReset,
+    WinnerDeclared(u128),
+    /// A ball that was still on the board when every ball stopped moving or the drop timed out.
+    BallStuck(u128),
 }
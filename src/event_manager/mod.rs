@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crossbeam::channel::{Receiver, Sender};
 use eyre::Result;
 
-use self::event::Event;
+use self::event::{Event, EventKind};
 
 pub mod event;
 
@@ -11,7 +11,7 @@ pub mod event;
 pub struct EventManager {
     events_receiver: Receiver<Event>,
     events_sender: Sender<Event>,
-    subscribers: HashMap<String, Vec<Sender<Event>>>,
+    subscribers: HashMap<EventKind, Vec<Sender<Event>>>,
 }
 
 impl EventManager {
@@ -24,17 +24,17 @@ impl EventManager {
         }
     }
 
-    #[allow(dead_code)]
-    pub fn subscribe(&mut self, event_name: String) -> Receiver<Event> {
+    pub fn subscribe(&mut self, event_kind: EventKind) -> Receiver<Event> {
         let (sender, receiver) = crossbeam::channel::unbounded();
-        self.insert_subscriber(sender, event_name);
+        self.insert_subscriber(sender, event_kind);
         receiver
     }
 
-    pub fn subscribe_many(&mut self, event_names: Vec<String>) -> Receiver<Event> {
+    /// One receiver for several kinds of event, which arrive in the order they were sent.
+    pub fn subscribe_many(&mut self, event_kinds: &[EventKind]) -> Receiver<Event> {
         let (sender, receiver) = crossbeam::channel::unbounded();
-        for event_name in event_names {
-            self.insert_subscriber(sender.clone(), event_name);
+        for event_kind in event_kinds {
+            self.insert_subscriber(sender.clone(), *event_kind);
         }
 
         receiver
@@ -44,25 +44,116 @@ impl EventManager {
         self.events_sender.clone()
     }
 
+    /// Delivers every queued event, in the order they were sent, to every subscriber of that
+    /// event. Events nobody subscribed to are dropped.
     pub fn update(&mut self) -> Result<()> {
-        loop {
-            let event = if let Ok(event) = self.events_receiver.try_recv() {
-                event
-            } else {
-                return Ok(());
-            };
-
-            if let Some(subscribers) = self.subscribers.get(event.as_ref()) {
+        while let Ok(event) = self.events_receiver.try_recv() {
+            if let Some(subscribers) = self.subscribers.get(&EventKind::from(event)) {
                 for subscriber in subscribers {
                     subscriber.send(event)?;
                 }
-                return Ok(());
             }
         }
+        Ok(())
     }
 
-    fn insert_subscriber(&mut self, sender: Sender<Event>, event_name: String) {
-        let subscriber_list = self.subscribers.entry(event_name).or_default();
+    fn insert_subscriber(&mut self, sender: Sender<Event>, event_kind: EventKind) {
+        let subscriber_list = self.subscribers.entry(event_kind).or_default();
         subscriber_list.push(sender);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ggez::event::{KeyCode, MouseButton};
+
+    use super::*;
+
+    fn received_keys(receiver: &Receiver<Event>) -> Vec<KeyCode> {
+        receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                Event::KeyPressed(keycode) => Some(keycode),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn update_dispatches_every_queued_event_in_order() {
+        let mut event_manager = EventManager::new();
+        let receiver = event_manager.subscribe(EventKind::KeyPressed);
+        let sender = event_manager.get_sender();
+        for keycode in [KeyCode::A, KeyCode::B, KeyCode::C].iter() {
+            sender.send(Event::KeyPressed(*keycode)).unwrap();
+        }
+
+        event_manager.update().unwrap();
+
+        assert_eq!(
+            received_keys(&receiver),
+            vec![KeyCode::A, KeyCode::B, KeyCode::C]
+        );
+    }
+
+    #[test]
+    fn update_delivers_to_every_subscriber() {
+        let mut event_manager = EventManager::new();
+        let first = event_manager.subscribe(EventKind::KeyPressed);
+        let second = event_manager.subscribe(EventKind::KeyPressed);
+        let sender = event_manager.get_sender();
+        sender.send(Event::KeyPressed(KeyCode::Space)).unwrap();
+        sender.send(Event::KeyPressed(KeyCode::R)).unwrap();
+
+        event_manager.update().unwrap();
+
+        assert_eq!(received_keys(&first), vec![KeyCode::Space, KeyCode::R]);
+        assert_eq!(received_keys(&second), vec![KeyCode::Space, KeyCode::R]);
+    }
+
+    #[test]
+    fn events_without_subscribers_do_not_hold_up_the_queue() {
+        let mut event_manager = EventManager::new();
+        let receiver = event_manager.subscribe(EventKind::KeyPressed);
+        let sender = event_manager.get_sender();
+        sender.send(Event::MouseClicked(MouseButton::Left)).unwrap();
+        sender.send(Event::KeyPressed(KeyCode::Space)).unwrap();
+
+        event_manager.update().unwrap();
+
+        assert_eq!(received_keys(&receiver), vec![KeyCode::Space]);
+    }
+
+    #[test]
+    fn subscribe_many_keeps_events_of_different_types_in_order() {
+        let mut event_manager = EventManager::new();
+        let receiver =
+            event_manager.subscribe_many(&[EventKind::KeyPressed, EventKind::MouseClicked]);
+        let sender = event_manager.get_sender();
+        sender.send(Event::KeyPressed(KeyCode::A)).unwrap();
+        sender.send(Event::MouseClicked(MouseButton::Left)).unwrap();
+        sender.send(Event::KeyPressed(KeyCode::B)).unwrap();
+
+        event_manager.update().unwrap();
+
+        let events: Vec<Event> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Event::KeyPressed(KeyCode::A)));
+        assert!(matches!(events[1], Event::MouseClicked(MouseButton::Left)));
+        assert!(matches!(events[2], Event::KeyPressed(KeyCode::B)));
+    }
+
+    #[test]
+    fn events_sent_after_update_wait_for_the_next_one() {
+        let mut event_manager = EventManager::new();
+        let receiver = event_manager.subscribe(EventKind::KeyPressed);
+        let sender = event_manager.get_sender();
+
+        event_manager.update().unwrap();
+        sender.send(Event::KeyPressed(KeyCode::A)).unwrap();
+        assert!(received_keys(&receiver).is_empty());
+
+        event_manager.update().unwrap();
+        assert_eq!(received_keys(&receiver), vec![KeyCode::A]);
+    }
+}
@@ -0,0 +1,192 @@
+use ggez::graphics::{Color, Rect};
+use rusttype::{point, Font, Scale};
+
+/// A small CPU rasterizer for the handful of shapes the board is made of. Everything is given in
+/// world coordinates and multiplied by `scale` so frames can be rendered smaller than the board.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    scale: f32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(world_width: f32, world_height: f32, scale: f32) -> Self {
+        let width = (world_width * scale).round() as u32;
+        let height = (world_height * scale).round() as u32;
+        Self {
+            width,
+            height,
+            scale,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        let (red, green, blue, alpha) = color.to_rgba();
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[red, green, blue, alpha]);
+        }
+    }
+
+    pub fn fill_circle(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        self.circle(x, y, radius, None, color);
+    }
+
+    pub fn stroke_circle(&mut self, x: f32, y: f32, radius: f32, thickness: f32, color: Color) {
+        self.circle(x, y, radius, Some(thickness), color);
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.fill_rotated_rect(rect, 0.0, color);
+    }
+
+    /// Fills `rect` rotated by `rotation` radians around the world origin, which is what
+    /// drawing a mesh with `DrawParam::new().rotation(rotation)` does in the window.
+    pub fn fill_rotated_rect(&mut self, rect: Rect, rotation: f32, color: Color) {
+        let (sin, cos) = rotation.sin_cos();
+        let corners: Vec<(f32, f32)> = [
+            (rect.x, rect.y),
+            (rect.x + rect.w, rect.y),
+            (rect.x + rect.w, rect.y + rect.h),
+            (rect.x, rect.y + rect.h),
+        ]
+        .iter()
+        .map(|(x, y)| {
+            (
+                (x * cos - y * sin) * self.scale,
+                (x * sin + y * cos) * self.scale,
+            )
+        })
+        .collect();
+
+        let min_x = corners
+            .iter()
+            .map(|c| c.0)
+            .fold(f32::MAX, f32::min)
+            .max(0.0) as u32;
+        let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max).ceil() as i64;
+        let min_y = corners
+            .iter()
+            .map(|c| c.1)
+            .fold(f32::MAX, f32::min)
+            .max(0.0) as u32;
+        let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max).ceil() as i64;
+
+        for pixel_y in min_y..(max_y.max(0) as u32).min(self.height) {
+            for pixel_x in min_x..(max_x.max(0) as u32).min(self.width) {
+                let center = (pixel_x as f32 + 0.5, pixel_y as f32 + 0.5);
+                if is_inside_convex(&corners, center) {
+                    self.blend(pixel_x, pixel_y, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Draws `text` with its top left corner at `x`, `y`, both already in pixels.
+    pub fn draw_text(&mut self, font: &Font, text: &str, size: f32, x: f32, y: f32, color: Color) {
+        let scale = Scale::uniform(size);
+        let ascent = font.v_metrics(scale).ascent;
+        for glyph in font.layout(text, scale, point(x, y + ascent)) {
+            let bounding_box = if let Some(bounding_box) = glyph.pixel_bounding_box() {
+                bounding_box
+            } else {
+                continue;
+            };
+            glyph.draw(|glyph_x, glyph_y, coverage| {
+                let pixel_x = bounding_box.min.x + glyph_x as i32;
+                let pixel_y = bounding_box.min.y + glyph_y as i32;
+                if pixel_x >= 0 && pixel_y >= 0 {
+                    self.blend(pixel_x as u32, pixel_y as u32, color, coverage);
+                }
+            });
+        }
+    }
+
+    /// Width and height of `text` in pixels.
+    pub fn measure_text(font: &Font, text: &str, size: f32) -> (f32, f32) {
+        let scale = Scale::uniform(size);
+        let v_metrics = font.v_metrics(scale);
+        let width = font
+            .layout(text, scale, point(0.0, 0.0))
+            .last()
+            .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+            .unwrap_or(0.0);
+        (width, v_metrics.ascent - v_metrics.descent)
+    }
+
+    fn circle(&mut self, x: f32, y: f32, radius: f32, thickness: Option<f32>, color: Color) {
+        let x = x * self.scale;
+        let y = y * self.scale;
+        let radius = radius * self.scale;
+        let inner_radius = thickness.map(|thickness| radius - thickness * self.scale);
+
+        let min_x = (x - radius - 1.0).max(0.0) as u32;
+        let max_x = ((x + radius + 1.0).max(0.0) as u32).min(self.width);
+        let min_y = (y - radius - 1.0).max(0.0) as u32;
+        let max_y = ((y + radius + 1.0).max(0.0) as u32).min(self.height);
+
+        for pixel_y in min_y..max_y {
+            for pixel_x in min_x..max_x {
+                let distance = ((pixel_x as f32 + 0.5 - x).powi(2)
+                    + (pixel_y as f32 + 0.5 - y).powi(2))
+                .sqrt();
+                let mut coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+                if let Some(inner_radius) = inner_radius {
+                    coverage *= (distance - inner_radius + 0.5).clamp(0.0, 1.0);
+                }
+                if coverage > 0.0 {
+                    self.blend(pixel_x, pixel_y, color, coverage);
+                }
+            }
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: Color, coverage: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let index = ((y * self.width + x) * 4) as usize;
+        let alpha = color.a * coverage;
+        let source = [color.r, color.g, color.b];
+        for (channel, source) in source.iter().enumerate() {
+            let destination = self.pixels[index + channel] as f32 / 255.0;
+            let blended = source * alpha + destination * (1.0 - alpha);
+            self.pixels[index + channel] = (blended * 255.0).round() as u8;
+        }
+        self.pixels[index + 3] = 255;
+    }
+}
+
+fn is_inside_convex(corners: &[(f32, f32)], point: (f32, f32)) -> bool {
+    let mut sign = 0.0;
+    for index in 0..corners.len() {
+        let (ax, ay) = corners[index];
+        let (bx, by) = corners[(index + 1) % corners.len()];
+        let cross = (bx - ax) * (point.1 - ay) - (by - ay) * (point.0 - ax);
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+    true
+}
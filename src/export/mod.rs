@@ -0,0 +1,244 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use eyre::{bail, eyre, Result};
+use ggez::graphics::{Rect, BLACK};
+use rusttype::Font;
+
+use crate::choices::Choice;
+use crate::config::config_struct::Config;
+use crate::draw_data::DataType;
+use crate::event_manager::event::EventKind;
+use crate::event_manager::EventManager;
+use crate::simulation::{Outcome, Simulation};
+
+use self::canvas::Canvas;
+
+pub mod canvas;
+
+const FALLBACK_FONT_PATHS: [&str; 4] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// How an offline render of a drop should be written.
+pub struct ExportOptions {
+    /// A `.gif` file, or a directory that numbered PNG frames are written into.
+    pub path: String,
+    /// Render a frame every this many simulation steps.
+    pub step_interval: u64,
+    /// Frame size relative to the board size in the config.
+    pub scale: f32,
+    /// TrueType font for the winner banner. Falls back to `winner_font` from the config and
+    /// then a few common system fonts.
+    pub font_path: Option<String>,
+    /// How long to keep rendering after the winner is decided so the banner is visible.
+    pub hold_seconds: f32,
+}
+
+/// Runs the drop headless and rasterizes it on the CPU, so it works without a GPU or display.
+pub fn export(
+    config: &Config,
+    choices: &[Choice],
+    drop_at_step: u64,
+    options: &ExportOptions,
+) -> Result<Outcome> {
+    // Without a ball the drop is never decided and frames would be written forever
+    if choices.is_empty() {
+        bail!("there must be at least one choice to drop");
+    }
+    if options.step_interval == 0 {
+        bail!("frames must be rendered at least every step");
+    }
+
+    let font_path = options.font_path.as_ref().or(config.winner_font.as_ref());
+    let font = load_font(font_path)?;
+    if font.is_none() {
+        eprintln!("No font found, the winner banner will be rendered without text");
+    }
+
+    let mut event_manager = EventManager::new();
+    let events = event_manager.subscribe(EventKind::Intersection);
+    let mut simulation = Simulation::new(config, &mut event_manager);
+    simulation.setup(choices);
+
+    let mut writer = FrameWriter::new(options, config)?;
+    let mut canvas = Canvas::new(config.width, config.height, options.scale);
+    let hold_steps = (options.hold_seconds * config.steps_per_second as f32) as u64;
+    let mut outcome = None;
+    let mut steps_after_winner = 0;
+
+    while steps_after_winner <= hold_steps {
+        if simulation.current_step() % options.step_interval == 0 {
+            render_frame(&mut canvas, &mut simulation, config, choices, font.as_ref());
+            writer.write(&canvas)?;
+        }
+        simulation.advance(&mut event_manager, &events, drop_at_step)?;
+
+        if outcome.is_none() {
+            outcome = simulation.outcome();
+        } else {
+            steps_after_winner += 1;
+        }
+    }
+
+    Ok(outcome.unwrap())
+}
+
+/// The same shapes, colours and winner banner that `MainState::draw` puts on screen.
+fn render_frame(
+    canvas: &mut Canvas,
+    simulation: &mut Simulation,
+    config: &Config,
+    choices: &[Choice],
+    font: Option<&Font>,
+) {
+    canvas.clear(config.background_color);
+    let winner = simulation.winner();
+    let draw_data = &mut simulation.draw_data;
+    let mut balls = vec![];
+    for (_handle, body) in simulation.physics.bodies.iter() {
+        let position = body.world_com;
+        let id = body.user_data;
+        match draw_data.get_type(id) {
+            DataType::Ball => balls.push((position, id)),
+            DataType::Wall => {
+                let color = draw_data.get_color(id);
+                canvas.fill_rect(draw_data.get_rectangle(id), color);
+            }
+            DataType::Nail => {
+                canvas.fill_circle(
+                    position.x,
+                    position.y,
+                    config.nail_radius,
+                    config.nail_color,
+                );
+            }
+            DataType::Collector => {
+                let rect = draw_data.get_rectangle(id);
+                let rotation = draw_data.get_rotation(id);
+                canvas.fill_rotated_rect(rect, rotation, config.wall_color);
+            }
+            DataType::Unknown => {}
+        }
+    }
+    for (position, id) in balls {
+        let color = draw_data.get_color(id);
+        let radius = draw_data.get_radius(id);
+        canvas.fill_circle(position.x, position.y, radius, color);
+        canvas.stroke_circle(position.x, position.y, radius, 2.0, BLACK);
+    }
+
+    let winner = if let Some(winner) = winner {
+        winner
+    } else {
+        return;
+    };
+    let color = draw_data.get_color(winner);
+    canvas.fill_rect(
+        Rect::new(0.0, 0.0, config.width, config.height),
+        config.winning_background_color(&color),
+    );
+
+    if let Some(font) = font {
+        let message = simulation.winner_message(choices).unwrap();
+        let size = config.winner_font_size * canvas.scale();
+        let lines: Vec<(&str, f32, f32)> = message
+            .lines()
+            .map(|line| {
+                let (line_width, line_height) = Canvas::measure_text(font, line, size);
+                (line, line_width, line_height.max(size))
+            })
+            .collect();
+        let block_height: f32 = lines.iter().map(|(_, _, line_height)| line_height).sum();
+        let center_x = canvas.width() as f32 * config.winner_position[0];
+        let mut y = canvas.height() as f32 * config.winner_position[1] - block_height / 2.0;
+        for (line, line_width, line_height) in lines {
+            canvas.draw_text(font, line, size, center_x - line_width / 2.0, y, color);
+            y += line_height;
+        }
+    }
+}
+
+fn load_font(font_path: Option<&String>) -> Result<Option<Font<'static>>> {
+    let path = if let Some(path) = font_path {
+        Some(path.as_str())
+    } else {
+        FALLBACK_FONT_PATHS
+            .iter()
+            .copied()
+            .find(|path| Path::new(path).exists())
+    };
+    let path = if let Some(path) = path {
+        path
+    } else {
+        return Ok(None);
+    };
+
+    let font =
+        Font::try_from_vec(fs::read(path)?).ok_or_else(|| eyre!("{} is not a valid font", path))?;
+    Ok(Some(font))
+}
+
+enum FrameWriter {
+    Gif {
+        encoder: gif::Encoder<File>,
+        delay: u16,
+    },
+    Png {
+        directory: String,
+        frame: u32,
+    },
+}
+
+impl FrameWriter {
+    fn new(options: &ExportOptions, config: &Config) -> Result<Self> {
+        let width = (config.width * options.scale).round() as u16;
+        let height = (config.height * options.scale).round() as u16;
+        if options.path.to_lowercase().ends_with(".gif") {
+            let file = File::create(&options.path)?;
+            let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+            // GIF delays are in hundredths of a second
+            let delay = (options.step_interval as f32 / config.steps_per_second as f32 * 100.0)
+                .round() as u16;
+            Ok(FrameWriter::Gif { encoder, delay })
+        } else {
+            fs::create_dir_all(&options.path)?;
+            Ok(FrameWriter::Png {
+                directory: options.path.clone(),
+                frame: 0,
+            })
+        }
+    }
+
+    fn write(&mut self, canvas: &Canvas) -> Result<()> {
+        match self {
+            FrameWriter::Gif { encoder, delay } => {
+                let mut pixels = canvas.pixels().to_vec();
+                let mut frame = gif::Frame::from_rgba_speed(
+                    canvas.width() as u16,
+                    canvas.height() as u16,
+                    &mut pixels,
+                    10,
+                );
+                frame.delay = *delay;
+                encoder.write_frame(&frame)?;
+            }
+            FrameWriter::Png { directory, frame } => {
+                let path = Path::new(directory).join(format!("frame_{:05}.png", frame));
+                image::save_buffer(
+                    path,
+                    canvas.pixels(),
+                    canvas.width(),
+                    canvas.height(),
+                    image::ColorType::Rgba8,
+                )?;
+                *frame += 1;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
 use ggez::graphics::{Font, Scale, Text};
-use ggez::Context;
 
-pub fn create_winner_text(context: &mut Context, name: &str) -> Text {
-    let mut winner = Text::new(format!("{} Won!!!", name));
-    let font = Font::default();
-    let scale = Scale::uniform(72.0);
+pub fn create_winner_text(message: &str, font: Font, size: f32) -> Text {
+    let mut winner = Text::new(message);
+    let scale = Scale::uniform(size);
     winner.set_font(font, scale);
     winner
 }
+
+/// Fills in `{name}`, `{rank}` and `{group}` in the configured winner message.
+pub fn format_winner_message(template: &str, name: &str, rank: usize, group: &str) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{rank}", &rank.to_string())
+        .replace("{group}", group)
+}
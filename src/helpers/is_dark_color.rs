@@ -1,6 +1,29 @@
 use ggez::graphics::Color;
 
+/// Whether white text reads better on top of `color` than black text does.
 pub fn is_dark_color(color: &Color) -> bool {
-    let average = (color.r + color.g + color.b) / 3.0;
-    average < 0.5
+    let luminance = relative_luminance(color);
+    contrast_ratio(1.0, luminance) > contrast_ratio(luminance, 0.0)
+}
+
+/// The WCAG relative luminance of a colour, from 0 for black to 1 for white.
+pub fn relative_luminance(color: &Color) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.039_28 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// The WCAG contrast ratio between two relative luminances, from 1 to 21.
+pub fn contrast_ratio(luminance1: f32, luminance2: f32) -> f32 {
+    let (lighter, darker) = if luminance1 > luminance2 {
+        (luminance1, luminance2)
+    } else {
+        (luminance2, luminance1)
+    };
+    (lighter + 0.05) / (darker + 0.05)
 }
@@ -0,0 +1,31 @@
+use eyre::Result;
+use ggez::graphics::Image;
+use ggez::Context;
+use image::imageops::{self, FilterType};
+
+/// Loads a PNG or JPEG, crops it to a centered square and clears everything outside of the
+/// inscribed circle so it can be drawn on top of a ball.
+pub fn load_avatar(context: &mut Context, path: &str, size: u32) -> Result<Image> {
+    let image = image::open(path)?.to_rgba8();
+    let side = image.width().min(image.height());
+    let x = (image.width() - side) / 2;
+    let y = (image.height() - side) / 2;
+    let square = imageops::crop_imm(&image, x, y, side, side).to_image();
+    let mut avatar = imageops::resize(&square, size, size, FilterType::Triangle);
+
+    let radius = size as f32 / 2.0;
+    for (pixel_x, pixel_y, pixel) in avatar.enumerate_pixels_mut() {
+        let distance = ((pixel_x as f32 + 0.5 - radius).powi(2)
+            + (pixel_y as f32 + 0.5 - radius).powi(2))
+        .sqrt();
+        let coverage = (radius - distance).clamp(0.0, 1.0);
+        pixel.0[3] = (pixel.0[3] as f32 * coverage) as u8;
+    }
+
+    Ok(Image::from_rgba8(
+        context,
+        size as u16,
+        size as u16,
+        &avatar.into_raw(),
+    )?)
+}
@@ -1,5 +1,8 @@
 // pub mod point;
 pub mod create_winner_text;
 pub mod is_dark_color;
+pub mod load_avatar;
+pub mod palette;
 pub mod serde_color;
+pub mod serde_tags;
 pub mod vector2;
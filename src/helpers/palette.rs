@@ -0,0 +1,55 @@
+use ggez::graphics::{Color, BLACK};
+
+/// Colours handed out in order to choices that don't set their own.
+const DEFAULT_PALETTE: [[u8; 3]; 10] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+    [210, 245, 60],
+    [250, 190, 190],
+];
+
+/// The Okabe-Ito palette, which stays distinguishable with the common colour vision
+/// deficiencies.
+const COLOUR_BLIND_SAFE_PALETTE: [[u8; 3]; 8] = [
+    [230, 159, 0],
+    [86, 180, 233],
+    [0, 158, 115],
+    [240, 228, 66],
+    [0, 114, 178],
+    [213, 94, 0],
+    [204, 121, 167],
+    [0, 0, 0],
+];
+
+/// The colour of the ball for a choice that doesn't set one. Balls have always been black by
+/// default, the palette is only used in accessibility mode.
+pub fn default_choice_color(index: usize, accessibility: bool) -> Color {
+    if accessibility {
+        palette_color(index, true)
+    } else {
+        BLACK
+    }
+}
+
+pub fn palette_len(colour_blind_safe: bool) -> usize {
+    if colour_blind_safe {
+        COLOUR_BLIND_SAFE_PALETTE.len()
+    } else {
+        DEFAULT_PALETTE.len()
+    }
+}
+
+pub fn palette_color(index: usize, colour_blind_safe: bool) -> Color {
+    let [red, green, blue] = if colour_blind_safe {
+        COLOUR_BLIND_SAFE_PALETTE[index % COLOUR_BLIND_SAFE_PALETTE.len()]
+    } else {
+        DEFAULT_PALETTE[index % DEFAULT_PALETTE.len()]
+    };
+    Color::from_rgb(red, green, blue)
+}
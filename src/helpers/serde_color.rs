@@ -1,12 +1,20 @@
 use ggez::graphics::Color;
 
+/// Accepts `[r, g, b]` or `[r, g, b, a]` with every channel from 0 to 255.
 pub fn deserialize<'de, D>(deser: D) -> Result<Color, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
+    use serde::de::Error as _;
     use serde::Deserialize as _;
-    let [r, g, b] = <[u8; 3]>::deserialize(deser)?;
-    Ok(Color::from_rgb(r, g, b))
+    match <Vec<u8>>::deserialize(deser)?.as_slice() {
+        [r, g, b] => Ok(Color::from_rgb(*r, *g, *b)),
+        [r, g, b, a] => Ok(Color::from_rgba(*r, *g, *b, *a)),
+        channels => Err(D::Error::invalid_length(
+            channels.len(),
+            &"a color with 3 or 4 channels",
+        )),
+    }
 }
 
 pub fn serialize<S>(color: &Color, ser: S) -> Result<S::Ok, S::Error>
@@ -14,10 +22,14 @@ where
     S: serde::Serializer,
 {
     use serde::ser::SerializeSeq as _;
-    let mut seq = ser.serialize_seq(Some(3))?;
-    let (red, green, blue) = &color.to_rgb();
+    let (red, green, blue, alpha) = color.to_rgba();
+    let has_alpha = alpha < 255;
+    let mut seq = ser.serialize_seq(Some(if has_alpha { 4 } else { 3 }))?;
     seq.serialize_element(&red)?;
     seq.serialize_element(&green)?;
     seq.serialize_element(&blue)?;
+    if has_alpha {
+        seq.serialize_element(&alpha)?;
+    }
     seq.end()
 }
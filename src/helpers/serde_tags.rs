@@ -0,0 +1,67 @@
+use std::fmt;
+
+use serde::de::{SeqAccess, Visitor};
+
+/// Accepts a list of tags, or one string with the tags separated by `;` so they fit in a
+/// single CSV column.
+pub fn deserialize<'de, D>(deser: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deser.deserialize_any(TagsVisitor)
+}
+
+struct TagsVisitor;
+
+impl<'de> Visitor<'de> for TagsVisitor {
+    type Value = Vec<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of tags or a string of tags separated by ;")
+    }
+
+    fn visit_str<E>(self, tags: &str) -> Result<Self::Value, E> {
+        Ok(tags
+            .split(';')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_owned)
+            .collect())
+    }
+
+    // CSV fields that look like numbers or booleans arrive as those instead of as strings
+    fn visit_bool<E>(self, tag: bool) -> Result<Self::Value, E> {
+        Ok(vec![tag.to_string()])
+    }
+
+    fn visit_i64<E>(self, tag: i64) -> Result<Self::Value, E> {
+        Ok(vec![tag.to_string()])
+    }
+
+    fn visit_u64<E>(self, tag: u64) -> Result<Self::Value, E> {
+        Ok(vec![tag.to_string()])
+    }
+
+    fn visit_f64<E>(self, tag: f64) -> Result<Self::Value, E> {
+        Ok(vec![tag.to_string()])
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(vec![])
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(vec![])
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut tags = vec![];
+        while let Some(tag) = seq.next_element::<String>()? {
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::Range;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 pub struct Vector2 {
     data: nalgebra::Vector2<f32>,
@@ -15,8 +15,7 @@ impl Vector2 {
         Self { data: vector2 }
     }
 
-    pub fn new_random(x_range: Range<f32>, y_range: Range<f32>) -> Self {
-        let mut rng = thread_rng();
+    pub fn new_random(rng: &mut impl Rng, x_range: Range<f32>, y_range: Range<f32>) -> Self {
         Self::new(rng.gen_range(x_range), rng.gen_range(y_range))
     }
 
@@ -0,0 +1,58 @@
+use ggez::graphics;
+use ggez::{Context, GameResult};
+
+use crate::config::config_struct::Config;
+use crate::draw_data::DrawData;
+use crate::panel::{self, PADDING, ROW_HEIGHT};
+use crate::physics::ball_tracker::BallTracker;
+
+/// Every ball that is still falling, closest to the finish first.
+pub fn racing_order(ball_tracker: &BallTracker, finished: &[u128]) -> Vec<u128> {
+    let mut racing: Vec<(u128, f32)> = ball_tracker
+        .ids()
+        .into_iter()
+        .filter(|id| !finished.contains(id))
+        .filter_map(|id| ball_tracker.get(id).map(|ball| (id, ball.y)))
+        .collect();
+    racing.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    racing.into_iter().map(|(id, _)| id).collect()
+}
+
+/// A panel on the right of the window listing the finished balls with their rank, followed by
+/// the balls still falling. Drawn in screen coordinates.
+pub fn draw(
+    context: &mut Context,
+    config: &Config,
+    draw_data: &mut DrawData,
+    finished: &[u128],
+    racing: &[u128],
+) -> GameResult {
+    let (window_width, window_height) = graphics::drawable_size(context);
+    let panel_x = window_width - config.leaderboard_width;
+    let text_color =
+        panel::draw_background(context, config, panel_x, config.leaderboard_width, 0.8)?;
+
+    let mut rows: Vec<(Option<u128>, String)> = vec![];
+    if !finished.is_empty() {
+        rows.push((None, "Finished".to_owned()));
+        for (rank, id) in finished.iter().enumerate() {
+            let name = draw_data.get_name(*id).unwrap();
+            rows.push((Some(*id), format!("{}. {}", rank + 1, name)));
+        }
+    }
+    if !racing.is_empty() {
+        rows.push((None, "Falling".to_owned()));
+        for id in racing {
+            rows.push((Some(*id), draw_data.get_name(*id).unwrap().clone()));
+        }
+    }
+
+    let max_rows = ((window_height - PADDING * 2.0) / ROW_HEIGHT) as usize;
+    let mut y = PADDING;
+    for (id, row) in rows.into_iter().take(max_rows) {
+        let swatch = id.map(|id| draw_data.get_color(id));
+        panel::draw_row(context, swatch, &row, panel_x + PADDING, y, text_color)?;
+        y += ROW_HEIGHT;
+    }
+    Ok(())
+}
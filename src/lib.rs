@@ -1,325 +1,854 @@
 use core::f32;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
 
-use choices::Choice;
-use config::config_struct::Config;
+use audio::{Audio, GgezAudio};
+use camera::Camera;
+use config::config_struct::{Config, LabelMode};
 use crossbeam::channel::{Receiver, Sender};
 use draw_data::{DataType, DrawData};
-use event_manager::event::Event;
+use editor::{Editor, EditorAction};
+use effects::Effects;
+use event_manager::event::{Event, EventKind};
 use event_manager::EventManager;
-use eyre::{bail, Result};
-use ggez::event::{EventHandler, KeyCode, KeyMods};
+use eyre::Result;
+use ggez::conf::{WindowMode, WindowSetup};
+use ggez::event::{self, Button, EventHandler, KeyCode, KeyMods, MouseButton};
 use ggez::graphics::{
-    self, Color, DrawMode, DrawParam, Font, MeshBuilder, Rect, Scale, Text, BLACK,
+    self, DrawMode, DrawParam, Font, Image, MeshBuilder, Rect, Scale, Text, BLACK,
 };
-use ggez::{timer, Context};
+use ggez::input::gamepad::GamepadId;
+use ggez::input::keyboard;
+use ggez::{timer, Context, ContextBuilder};
 use helpers::is_dark_color::is_dark_color;
-use helpers::vector2::Vector2;
-use physics::Physics;
+use helpers::load_avatar::load_avatar;
+use replay::{Playback, Recording};
+use server::{ControlServer, ServerCommand};
+use simulation::Simulation;
+use winner_banner::WinnerBanner;
+use winner_hooks::WinnerHooks;
 
-use crate::choices::{load_choices_from_csv, load_choices_from_json, load_choices_from_stdin};
+use crate::choices::{
+    file_type_from_path, filter_choices, load_choices_from_file, load_choices_from_stdin,
+    save_choices, validate_choices,
+};
+
+pub use choices::{Choice, ChoiceFilter};
+pub use chooser::Chooser;
+pub use export::ExportOptions;
+pub use observer::DropObserver;
+pub use simulation::Outcome;
 
+mod audio;
+mod ball_patterns;
+mod camera;
 mod choices;
+mod chooser;
 pub mod config;
 mod draw_data;
+mod editor;
+mod effects;
 mod event_manager;
+mod export;
 mod helpers;
+mod leaderboard;
+mod observer;
+mod panel;
 mod physics;
+mod replay;
+mod server;
+mod simulation;
+mod winner_banner;
+mod winner_hooks;
+
+/// Avatars are rendered at a higher resolution than the ball so they stay sharp when zoomed in.
+const AVATAR_RESOLUTION: f32 = 4.0;
+const MIN_AVATAR_SIZE: u32 = 64;
+const WINNER_AVATAR_SIZE: u32 = 256;
+const COUNTDOWN_FONT_SIZE: f32 = 200.0;
 
 pub struct MainState {
     config: Config,
     choices: Vec<Choice>,
-    physics: Physics,
-    draw_data: DrawData,
-    floor_id: Option<u128>,
+    simulation: Simulation,
     event_manager: EventManager,
     send_events: Sender<Event>,
     events: Receiver<Event>,
-    winner: Option<(Text, Color)>,
+    winner: Option<WinnerBanner>,
+    winner_font: Font,
+    record_path: Option<String>,
+    playback: Option<Playback>,
+    camera: Camera,
+    labels: HashMap<u128, Text>,
+    avatars: HashMap<u128, Option<Image>>,
+    effects: Effects,
+    audio: Audio,
+    countdown_ends_at: Option<u64>,
+    observers: Vec<Box<dyn DropObserver>>,
+    observer_events: Receiver<Event>,
+    server: Option<ControlServer>,
+    editor: Editor,
+    /// Where the choices came from and in which format, so the editor can save them back.
+    choices_file: Option<(String, String)>,
+    /// Whether accessibility mode may announce the winner on stdout.
+    announce_on_stdout: bool,
 }
 
 impl MainState {
-    pub fn new(config: Config, choices_path: String, choice_file_type: String) -> Result<Self> {
+    /// A drop of the choices `filter` keeps. The rest stay in the editor, switched off, so
+    /// saving doesn't lose them.
+    pub fn new(config: Config, choices: Vec<Choice>, filter: &ChoiceFilter) -> Self {
+        let mut main_state = Self::with_choices(config, choices);
+        main_state.editor.set_enabled(&filter.keep);
+        main_state.choices = main_state.editor.enabled_choices();
+        main_state
+    }
+
+    /// Plays back a drop saved with `--record`. The floor is removed at the recorded step
+    /// instead of when Space is pressed.
+    pub fn from_recording(path: String) -> Result<Self> {
+        let recording = Recording::load(&path)?;
+        let mut config = recording.config.clone();
+        config.seed = Some(recording.seed);
+        // The winner was announced when the drop was recorded, and scrubbing back through the
+        // replay would announce it again every time
+        config.on_winner = None;
+        config.winner_webhook = None;
+        let mut main_state = Self::with_choices(config, recording.choices.clone());
+        main_state.playback = Some(Playback::new(recording));
+        Ok(main_state)
+    }
+
+    pub(crate) fn with_choices(config: Config, choices: Vec<Choice>) -> Self {
         let mut event_manager = EventManager::new();
-        let choices = if config.use_stdin {
-            load_choices_from_stdin()?
-        } else if choice_file_type.to_lowercase() == "json" {
-            load_choices_from_json(choices_path)?
-        } else if choice_file_type.to_lowercase() == "csv" {
-            load_choices_from_csv(choices_path)?
-        } else {
-            bail!("choices must be json or csv");
-        };
-        let physics = Physics::new(&config, &mut event_manager);
+        let simulation = Simulation::new(&config, &mut event_manager);
+        let effects = Effects::new(&config, &mut event_manager);
+        let audio = Audio::new(&config, &mut event_manager);
 
-        let events = event_manager.subscribe_many(vec![
-            "KeyPressed".to_owned(),
-            "IntersectionEvent".to_owned(),
+        let events = event_manager.subscribe_many(&[
+            EventKind::KeyPressed,
+            EventKind::TextInput,
+            EventKind::MouseClicked,
+            EventKind::GamepadButtonPressed,
+            EventKind::StartRequested,
+            EventKind::Intersection,
+        ]);
+        let observer_events = event_manager.subscribe_many(&[
+            EventKind::RoundReset,
+            EventKind::FloorRemoved,
+            EventKind::BallFinished,
+            EventKind::BallStuck,
+            EventKind::WinnerDeclared,
         ]);
 
-        Ok(Self {
+        let mut observers: Vec<Box<dyn DropObserver>> = vec![];
+        if let Some(winner_hooks) = WinnerHooks::new(&config) {
+            observers.push(Box::new(winner_hooks));
+        }
+
+        let editor = Editor::new(&choices);
+
+        Self {
+            camera: Camera::new(&config),
             config,
             choices,
-            physics,
-            draw_data: DrawData::new(),
+            simulation,
             send_events: event_manager.get_sender(),
-            floor_id: None,
             event_manager,
             events,
             winner: None,
-        })
-    }
-
-    pub fn setup(&mut self) {
-        self.create_choice_balls();
-        self.insert_floor();
-        self.create_nails();
-        self.create_walls();
-        self.create_collector(false);
-        self.create_collector(true);
-        self.create_winning_sensor();
-    }
-
-    fn create_choice_balls(&mut self) {
-        for choice in &self.choices {
-            let radius = self.config.choice_radius;
-            let start_x = self.config.wall_width + self.config.choice_radius * 2.0;
-            let end_x =
-                self.config.width - self.config.wall_width - self.config.choice_radius * 2.0;
-            let start_y = -(self.choices.len() as f32 * self.config.choice_radius);
-            let end_y = 0.0;
-            let position = Vector2::new_random(start_x..end_x, start_y..end_y);
-            let id = self
-                .physics
-                .insert_ball(position, radius, self.config.bounciness);
-            self.draw_data.insert_color(id, choice.color());
-            self.draw_data.insert_type(id, DataType::Ball);
-            self.draw_data.insert_name(id, choice.name.clone());
-        }
-    }
-
-    fn insert_floor(&mut self) {
-        let position = Vector2::new(self.config.width / 2.0, self.config.floor_position_y);
-        let id = self
-            .physics
-            .insert_wall(position, self.config.width, self.config.floor_height);
-        self.draw_data.insert_type(id, DataType::Wall);
-        self.draw_data.insert_color(id, self.config.floor_color);
-        let rect = Rect::new(
-            0.0,
-            self.config.floor_position_y - self.config.floor_height / 2.0,
-            self.config.width,
-            self.config.floor_height,
-        );
-        self.draw_data.insert_rectangle(id, rect);
-        self.floor_id = Some(id);
+            winner_font: Font::default(),
+            record_path: None,
+            playback: None,
+            labels: HashMap::new(),
+            avatars: HashMap::new(),
+            effects,
+            audio,
+            countdown_ends_at: None,
+            observers,
+            observer_events,
+            server: None,
+            editor,
+            choices_file: None,
+            announce_on_stdout: true,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The winner so far, `None` until the drop has been decided.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.simulation.outcome()
+    }
+
+    /// Opens a window sized from the config and runs the drop until the window is closed.
+    pub fn run(mut self) -> Result<Option<Outcome>> {
+        let (window_width, window_height) = self.config.window_size();
+        let window_mode = WindowMode::default().dimensions(window_width, window_height);
+        let window_setup = WindowSetup::default()
+            .title(&self.config.title)
+            .vsync(self.config.vsync);
+        let (mut context, mut event_loop) =
+            ContextBuilder::new("random_chooser_drop", "Brookzerker")
+                .window_mode(window_mode)
+                .window_setup(window_setup)
+                .build()?;
+        self.setup(&mut context)?;
+        let result = event::run(&mut context, &mut event_loop, &mut self);
+        for observer in self.observers.iter_mut() {
+            observer.finished();
+        }
+        result?;
+        Ok(self.outcome())
+    }
+
+    /// Let the editor save the choices back to `path`, written as `file_type`.
+    pub fn save_choices_to(&mut self, path: String, file_type: String) {
+        self.choices_file = Some((path, file_type));
+    }
+
+    /// Save a recording of this drop to `path` once a winner has been decided.
+    pub fn record_to(&mut self, path: String) {
+        self.record_path = Some(path);
+    }
+
+    /// Don't play any sounds, even if sound files are configured.
+    pub fn mute(&mut self) {
+        self.config.mute = true;
+    }
+
+    /// Don't print the winner announcement to stdout, for when stdout carries machine-readable
+    /// output.
+    pub fn quiet_stdout(&mut self) {
+        self.announce_on_stdout = false;
+    }
+
+    /// Receive callbacks as the drop progresses. Register observers before calling `setup` to
+    /// hear about the first round starting.
+    pub fn add_observer(&mut self, observer: Box<dyn DropObserver>) {
+        self.observers.push(observer);
+    }
+
+    pub fn setup(&mut self, context: &mut Context) -> Result<()> {
+        validate_choices(&self.choices, &self.config)?;
+        self.simulation.setup(&self.choices);
+        for observer in self.observers.iter_mut() {
+            observer.round_started(&self.choices);
+        }
+        if let Some(path) = &self.config.winner_font {
+            self.winner_font = Font::new_glyph_font_bytes(context, &fs::read(path)?)?;
+        }
+        if !self.config.mute {
+            self.audio
+                .set_backend(Box::new(GgezAudio::new(context, &self.config)?));
+        }
+        // New choices or a drop requested from outside would break the replay being played
+        if let (Some(control_port), None) = (self.config.control_port, &self.playback) {
+            self.server = Some(ControlServer::start(control_port, &mut self.event_manager)?);
+        }
+        if self.config.auto_start {
+            self.start_drop();
+        }
+        Ok(())
+    }
+
+    /// Removes the floor, after the configured countdown if there is one. Recordings remove
+    /// the floor on their own, so start triggers are ignored during playback.
+    fn start_drop(&mut self) {
+        if self.playback.is_some()
+            || self.countdown_ends_at.is_some()
+            || self.simulation.floor_removed_at().is_some()
+        {
+            return;
+        }
+
+        let countdown_steps = self.config.countdown_steps();
+        if countdown_steps == 0 {
+            self.remove_floor();
+        } else {
+            self.countdown_ends_at = Some(self.simulation.current_step() + countdown_steps);
+        }
+    }
+
+    fn update_countdown(&mut self) {
+        if let Some(countdown_ends_at) = self.countdown_ends_at {
+            if self.simulation.current_step() >= countdown_ends_at {
+                self.countdown_ends_at = None;
+                self.remove_floor();
+            }
+        }
+    }
+
+    fn draw_countdown(&self, context: &mut Context) -> ggez::GameResult {
+        let countdown_ends_at = if let Some(countdown_ends_at) = self.countdown_ends_at {
+            countdown_ends_at
+        } else {
+            return Ok(());
+        };
+
+        let steps_left = countdown_ends_at.saturating_sub(self.simulation.current_step());
+        let seconds_left = (steps_left as f32 / self.config.steps_per_second as f32).ceil() as u64;
+        let mut text = Text::new(seconds_left.max(1).to_string());
+        text.set_font(Font::default(), Scale::uniform(COUNTDOWN_FONT_SIZE));
+        let (text_width, text_height) = text.dimensions(context);
+        let (width, height) = graphics::drawable_size(context);
+        let color = if is_dark_color(&self.config.background_color) {
+            graphics::WHITE
+        } else {
+            BLACK
+        };
+        graphics::draw(
+            context,
+            &text,
+            DrawParam::new()
+                .dest([
+                    width / 2.0 - text_width as f32 / 2.0,
+                    height / 2.0 - text_height as f32 / 2.0,
+                ])
+                .color(color),
+        )
     }
 
     fn remove_floor(&mut self) {
-        let floor_id = if let Some(id) = self.floor_id {
+        self.simulation.remove_floor();
+    }
+
+    fn create_winner(&mut self, context: &mut Context) {
+        if self.winner.is_some() {
+            return;
+        }
+
+        let id = if let Some(id) = self.simulation.winner() {
             id
         } else {
             return;
         };
+        let message = self.simulation.winner_message(&self.choices).unwrap();
+        if self.config.accessibility && self.announce_on_stdout {
+            println!("Winner: {}", message);
+        }
+        let draw_data = &mut self.simulation.draw_data;
+        let avatar = draw_data
+            .get_image(id)
+            .and_then(|path| load_avatar(context, path, WINNER_AVATAR_SIZE).ok());
+        let color = draw_data.get_color(id);
+        self.winner = Some(WinnerBanner::new(message, color, avatar));
+        let (width, height) = graphics::drawable_size(context);
+        self.effects.start_confetti(
+            width * self.config.winner_position[0],
+            height * self.config.winner_position[1],
+            color,
+        );
+        if let (Some(playback), Some(name)) = (
+            self.playback.as_mut(),
+            self.simulation.draw_data.get_name(id),
+        ) {
+            playback.verify_winner(name);
+        }
+        self.save_recording(id);
+    }
 
-        let handle = self.physics.get_rigid_body_handle(floor_id).unwrap();
-        self.physics.remove(handle);
-        self.floor_id = None;
+    fn save_recording(&self, winner_id: u128) {
+        let path = if let (Some(path), None) = (&self.record_path, &self.playback) {
+            path
+        } else {
+            return;
+        };
+
+        let recording = Recording {
+            seed: self.simulation.seed(),
+            config: self.config.clone(),
+            choices: self.choices.clone(),
+            floor_removed_at: self.simulation.floor_removed_at(),
+            finished_at: Some(self.simulation.current_step()),
+            winner: self.simulation.draw_data.get_name(winner_id).cloned(),
+        };
+        if let Err(error) = recording.save(path) {
+            eprintln!("Could not save the recording to {}: {}", path, error);
+        }
+    }
+
+    fn step_simulation(&mut self) {
+        self.advance_simulation();
+        self.effects
+            .update(&self.simulation, 1.0 / self.config.steps_per_second as f32);
+        self.audio.update(&self.simulation);
+    }
+
+    /// Steps the simulation without the effects and audio that go with it.
+    fn advance_simulation(&mut self) {
+        if let Some(playback) = &self.playback {
+            if playback.recording.floor_removed_at == Some(self.simulation.current_step()) {
+                self.simulation.remove_floor();
+            }
+        }
+        self.simulation.step();
+        // Hand out the contacts from this step so sparks and plinks happen on the same frame
+        self.event_manager.update().unwrap();
     }
 
-    fn create_nails(&mut self) {
-        let mut y = self.config.floor_position_y * 2.0;
-        let space_between_x = self.config.width / self.config.nails_in_row as f32;
-        let space_between_y = (self.config.height - y) / self.config.rows_of_nails as f32;
+    /// Rebuilds the board from `choices` as if the drop had just opened. The seed only stays
+    /// the same when it is set in the config.
+    fn restart_round(&mut self, choices: Vec<Choice>) {
+        self.event_manager.update().unwrap();
+        while self.events.try_recv().is_ok() {}
+        // The old round's events refer to balls on the old board and the old choices, so they
+        // are passed on before either is replaced
+        self.notify_observers();
+        self.choices = choices;
+        self.simulation = Simulation::new(&self.config, &mut self.event_manager);
+        self.simulation.setup(&self.choices);
+        self.winner = None;
+        self.countdown_ends_at = None;
+        self.labels.clear();
+        self.avatars.clear();
+        // Ids start from 1 again on the new board, so old trails would point at other bodies
+        self.effects.clear();
+        self.send_events.send(Event::RoundReset).unwrap();
+        self.event_manager.update().unwrap();
+        self.notify_observers();
+    }
+
+    fn handle_server_commands(&mut self) {
+        let command = match &self.server {
+            Some(server) => server.try_command(),
+            None => return,
+        };
+        if let Some(ServerCommand::LoadChoices(choices)) = command {
+            if let Err(error) = validate_choices(&choices, &self.config) {
+                eprintln!("Ignoring the choices from the control server: {}", error);
+                return;
+            }
+            let keep = match filter_choices(&choices, &self.config) {
+                Ok(filter) => filter.keep,
+                Err(error) => {
+                    eprintln!("Could not filter the choices: {}", error);
+                    vec![true; choices.len()]
+                }
+            };
+            self.editor.set_choices(&choices);
+            self.editor.set_enabled(&keep);
+            self.restart_round(self.editor.enabled_choices());
+        }
+    }
 
-        for y_count in 0..self.config.rows_of_nails {
-            for x_count in 0..self.config.nails_in_row {
-                let offset = if y_count % 2 == 0 {
-                    space_between_x / 2.0
+    fn handle_editor_action(&mut self, action: EditorAction) {
+        match action {
+            EditorAction::None => {}
+            EditorAction::Apply => {
+                self.restart_round(self.editor.enabled_choices());
+            }
+            EditorAction::Save => {
+                let status = if let Some((path, file_type)) = &self.choices_file {
+                    match save_choices(path, file_type, &self.editor.all_choices()) {
+                        Ok(()) => format!("Saved to {}", path),
+                        Err(error) => format!("Could not save: {}", error),
+                    }
                 } else {
-                    0.0
+                    "Choices read from stdin can't be saved".to_owned()
                 };
-                let position = Vector2::new(space_between_x * x_count as f32 + offset, y);
-                let id = self.physics.insert_nail(position, self.config.nail_radius);
-                self.draw_data.insert_type(id, DataType::Nail);
+                self.editor.set_status(status);
             }
-            y += space_between_y;
         }
     }
 
-    fn create_walls(&mut self) {
-        let left_position = Vector2::new(self.config.wall_width / 2.0, self.config.height / 2.0);
-        let left_id = self.physics.insert_wall(
-            left_position,
-            self.config.wall_width,
-            self.config.height * 2.0,
-        );
-        let right_position = Vector2::new(
-            self.config.width - self.config.wall_width / 2.0,
-            self.config.height / 2.0,
-        );
-        let right_id = self.physics.insert_wall(
-            right_position,
-            self.config.wall_width,
-            self.config.height * 2.0,
-        );
-        self.draw_data.insert_type(left_id, DataType::Wall);
-        self.draw_data.insert_type(right_id, DataType::Wall);
-        self.draw_data.insert_rectangle(
-            left_id,
-            Rect::new(0.0, 0.0, self.config.wall_width, self.config.height),
-        );
-        self.draw_data.insert_rectangle(
-            right_id,
-            Rect::new(
-                self.config.width - self.config.wall_width,
-                0.0,
-                self.config.wall_width,
-                self.config.height,
-            ),
-        );
-        self.draw_data.insert_color(left_id, self.config.wall_color);
-        self.draw_data
-            .insert_color(right_id, self.config.wall_color);
+    /// Moves playback to `target_step`. Going backwards rebuilds the board from the seed and
+    /// simulates forward again, which works because the simulation is deterministic.
+    fn seek(&mut self, target_step: u64) {
+        if target_step < self.simulation.current_step() {
+            self.restart_round(self.choices.clone());
+        }
+
+        // Every plink and spark along the way would otherwise go off at once
+        while self.simulation.current_step() < target_step {
+            self.advance_simulation();
+            self.event_manager.update().unwrap();
+            self.notify_observers();
+            while let Ok(event) = self.events.try_recv() {
+                if let Event::Intersection(collider_handle1, collider_handle2) = event {
+                    self.simulation
+                        .handle_intersection(collider_handle1, collider_handle2);
+                }
+            }
+        }
+        self.effects.skip();
+        self.audio.skip();
     }
 
-    fn create_collector(&mut self, is_right: bool) {
-        let mut position = Vector2::new(
-            self.config.width / 4.0,
-            self.config.height - self.config.collector_offset_y,
-        );
-        if is_right {
-            *position.get_x_mut() += self.config.width / 2.0 + self.config.choice_radius * 4.0;
-        } else {
-            *position.get_x_mut() -= self.config.choice_radius;
+    fn notify_observers(&mut self) {
+        let simulation = &self.simulation;
+        let choices = &self.choices;
+        let choice = |id: u128| simulation.choice_index(id).map(|index| &choices[index]);
+        while let Ok(event) = self.observer_events.try_recv() {
+            for observer in self.observers.iter_mut() {
+                match event {
+                    Event::RoundReset => observer.round_started(choices),
+                    Event::FloorRemoved(step) => observer.floor_removed(step),
+                    Event::BallFinished(id, rank) => {
+                        if let Some(choice) = choice(id) {
+                            observer.ball_finished(choice, rank);
+                        }
+                    }
+                    Event::BallStuck(id) => {
+                        if let Some(choice) = choice(id) {
+                            observer.ball_stuck(choice);
+                        }
+                    }
+                    Event::WinnerDeclared(id) => {
+                        if let Some(choice) = choice(id) {
+                            observer.winner_declared(choice);
+                        }
+                    }
+                    _ => {}
+                }
+            }
         }
-        let width = self.config.width / 2.0;
-        let height = self.config.wall_width;
-        let rotation = if is_right {
-            -self.config.collector_rotation
-        } else {
-            self.config.collector_rotation
-        };
-        let draw_type = DataType::Collector;
-        let id = self.physics.insert_rotated_wall(
-            position,
-            width,
-            height,
-            rotation,
-            -self.config.collector_rotation_offset,
-        );
+    }
 
-        let rect_width = width + self.config.collector_rotation_offset;
-        let draw_rect = if is_right {
-            Rect::new(
-                self.config.width / 2.0 + self.config.choice_radius,
-                self.config.height + self.config.collector_offset_y * 0.5,
-                rect_width,
-                height,
-            )
+    fn lowest_ball_position(&self) -> Option<(f32, f32)> {
+        let ball_tracker = &self.simulation.physics.ball_tracker;
+        let lowest = ball_tracker.get(ball_tracker.lowest()?)?;
+        Some((lowest.x, lowest.y))
+    }
+
+    /// Draws each ball's avatar clipped to the ball and rotating with it. Balls without an
+    /// avatar, or whose image failed to load, keep their flat colour.
+    fn draw_avatars(&mut self, context: &mut Context) -> ggez::GameResult {
+        for (_handle, body) in self.simulation.physics.bodies.iter() {
+            let id = body.user_data;
+            let avatar = if let Some(avatar) =
+                get_avatar(&mut self.avatars, &self.simulation.draw_data, context, id)
+            {
+                avatar
+            } else {
+                continue;
+            };
+            let diameter = self.simulation.draw_data.get_radius(id) * 2.0;
+            let scale = diameter / avatar.width() as f32;
+            graphics::draw(
+                context,
+                avatar,
+                DrawParam::new()
+                    .dest([body.world_com.x, body.world_com.y])
+                    .offset([0.5, 0.5])
+                    .rotation(body.position().rotation.angle())
+                    .scale([scale, scale]),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draws the name of each ball above it, either for every ball, only the ball in the lead
+    /// or only the ball under the mouse. Must be called while the camera is applied.
+    fn draw_labels(
+        &mut self,
+        context: &mut Context,
+        balls: &[(u128, f32, f32, f32)],
+    ) -> ggez::GameResult {
+        let label_mode = if self.config.accessibility {
+            LabelMode::Always
         } else {
-            Rect::new(
-                0.0 - self.config.choice_radius,
-                self.config.height - self.config.collector_offset_y,
-                rect_width,
-                height,
-            )
+            self.config.label_mode
         };
+        let visible_ids: Vec<u128> = match label_mode {
+            LabelMode::Off => return Ok(()),
+            LabelMode::Always => balls.iter().map(|ball| ball.0).collect(),
+            LabelMode::Leader => self
+                .simulation
+                .physics
+                .ball_tracker
+                .lowest()
+                .into_iter()
+                .collect(),
+            LabelMode::Hover => {
+                let mouse = ggez::input::mouse::position(context);
+                let (window_width, window_height) = graphics::drawable_size(context);
+                let (mouse_x, mouse_y) =
+                    self.camera
+                        .screen_to_world(mouse.x, mouse.y, window_width, window_height);
+                balls
+                    .iter()
+                    .filter(|(_, x, y, radius)| {
+                        (x - mouse_x).powi(2) + (y - mouse_y).powi(2) <= radius.powi(2)
+                    })
+                    .map(|ball| ball.0)
+                    .collect()
+            }
+        };
+
+        let padding = 2.0;
+        for (id, x, y, radius) in balls {
+            if !visible_ids.contains(id) {
+                continue;
+            }
+
+            let draw_data = &mut self.simulation.draw_data;
+            let background_color = draw_data.get_color(*id);
+            let text_color = if is_dark_color(&background_color) {
+                graphics::WHITE
+            } else {
+                BLACK
+            };
+            let font_size = self.config.label_font_size;
+            let label = self.labels.entry(*id).or_insert_with(|| {
+                let mut label = Text::new(draw_data.get_name(*id).unwrap().as_str());
+                label.set_font(Font::default(), Scale::uniform(font_size));
+                label
+            });
+            let (label_width, label_height) = label.dimensions(context);
+            let label_x = x - label_width as f32 / 2.0;
+            let label_y = y - radius - label_height as f32 - padding * 2.0;
+            let background = MeshBuilder::new()
+                .rectangle(
+                    DrawMode::fill(),
+                    Rect::new(
+                        label_x - padding,
+                        label_y - padding,
+                        label_width as f32 + padding * 2.0,
+                        label_height as f32 + padding * 2.0,
+                    ),
+                    background_color,
+                )
+                .build(context)?;
+            graphics::draw(context, &background, DrawParam::new())?;
+            graphics::draw(
+                context,
+                label,
+                DrawParam::new().dest([label_x, label_y]).color(text_color),
+            )?;
+        }
+        Ok(())
+    }
 
-        self.draw_data.insert_rectangle(id, draw_rect);
-        self.draw_data.insert_rotation(id, rotation);
-        self.draw_data.insert_type(id, draw_type);
+    fn handle_playback_key(&mut self, keycode: KeyCode) {
+        let scrub_steps = self.config.steps_per_second as u64 * 2;
+        let current_step = self.simulation.current_step();
+        let playback = self.playback.as_mut().unwrap();
+        match keycode {
+            KeyCode::P => playback.toggle_pause(),
+            KeyCode::S => playback.cycle_slow_motion(),
+            KeyCode::Comma => self.seek(current_step.saturating_sub(scrub_steps)),
+            KeyCode::Period => self.seek(current_step + scrub_steps),
+            KeyCode::R => self.seek(0),
+            _ => {}
+        }
     }
+}
 
-    fn create_winning_sensor(&mut self) {
-        let sensor_width = self.config.width;
-        let sensor_height = self.config.choice_radius;
-        let sensor_position = Vector2::new(
-            self.config.width / 2.0,
-            self.config.height + sensor_height / 2.0,
-        );
+/// Runs a whole drop without opening a window, removing the floor once `drop_at_step` steps
+/// have been simulated.
+pub fn run_headless(config: Config, choices: Vec<Choice>, drop_at_step: u64) -> Result<Outcome> {
+    Chooser::new(config, choices)
+        .drop_at_step(drop_at_step)
+        .run_headless()
+}
 
-        let _id = self
-            .physics
-            .insert_sensor(sensor_position, sensor_width, sensor_height);
+/// Renders a whole drop to an animated GIF or a directory of PNG frames without a window or GPU.
+pub fn export_drop(
+    config: Config,
+    choices: Vec<Choice>,
+    drop_at_step: u64,
+    options: ExportOptions,
+) -> Result<Outcome> {
+    let outcome = export::export(&config, &choices, drop_at_step, &options)?;
+    if let Some(winner_hooks) = WinnerHooks::new(&config) {
+        if let Some(winner) = choices.iter().find(|choice| choice.name == outcome.winner) {
+            winner_hooks.notify(winner, 1);
+        }
     }
+    Ok(outcome)
+}
+
+/// Reads a choices file in one format and writes it in another, keeping every field the
+/// output format has room for. Formats are guessed from the file extensions when not given.
+/// Returns how many choices were converted.
+pub fn convert_choices(
+    input_path: String,
+    input_type: Option<String>,
+    output_path: String,
+    output_type: Option<String>,
+) -> Result<usize> {
+    let input_type = match input_type {
+        Some(input_type) => input_type,
+        None => file_type_from_path(&input_path)?,
+    };
+    let output_type = match output_type {
+        Some(output_type) => output_type,
+        None => file_type_from_path(&output_path)?,
+    };
+    let choices = load_choices_from_file(input_path, &input_type)?;
+    save_choices(&output_path, &output_type, &choices)?;
+    Ok(choices.len())
+}
+
+fn get_avatar<'a>(
+    avatars: &'a mut HashMap<u128, Option<Image>>,
+    draw_data: &DrawData,
+    context: &mut Context,
+    id: u128,
+) -> Option<&'a Image> {
+    match avatars.entry(id) {
+        Entry::Occupied(entry) => entry.into_mut().as_ref(),
+        Entry::Vacant(entry) => {
+            let path = draw_data.get_image(id)?;
+            let size = (draw_data.get_radius(id) * 2.0 * AVATAR_RESOLUTION) as u32;
+            let avatar = match load_avatar(context, path, size.max(MIN_AVATAR_SIZE)) {
+                Ok(avatar) => Some(avatar),
+                Err(error) => {
+                    eprintln!("Could not load avatar {}: {}", path, error);
+                    None
+                }
+            };
+            entry.insert(avatar).as_ref()
+        }
+    }
+}
+
+/// Reads every choice, from stdin when the config says so, and works out which of them the
+/// config's filters keep.
+pub fn load_choices(
+    config: &Config,
+    choices_path: String,
+    choice_file_type: String,
+) -> Result<(Vec<Choice>, ChoiceFilter)> {
+    let choices = if config.use_stdin {
+        load_choices_from_stdin()?
+    } else {
+        load_choices_from_file(choices_path, &choice_file_type)?
+    };
+    validate_choices(&choices, config)?;
+    let filter = filter_choices(&choices, config)?;
+    Ok((choices, filter))
 }
 
 impl EventHandler for MainState {
     fn update(&mut self, context: &mut ggez::Context) -> ggez::GameResult {
-        while timer::check_update_time(context, 60) {
-            self.physics.update();
+        self.handle_server_commands();
+        while timer::check_update_time(context, self.config.steps_per_second) {
+            let should_step = if let Some(playback) = self.playback.as_mut() {
+                playback.should_step()
+            } else {
+                true
+            };
+            if should_step {
+                self.step_simulation();
+                self.update_countdown();
+            }
             self.event_manager.update().unwrap();
 
-            if let Ok(event) = self.events.try_recv() {
+            while let Ok(event) = self.events.try_recv() {
                 match event {
+                    Event::KeyPressed(KeyCode::Tab) if self.playback.is_none() => {
+                        let action = self.editor.toggle();
+                        self.handle_editor_action(action);
+                    }
+                    Event::KeyPressed(keycode) if self.editor.open => {
+                        let ctrl = keyboard::is_mod_active(context, KeyMods::CTRL);
+                        let action =
+                            self.editor
+                                .handle_key(keycode, ctrl, self.config.accessibility);
+                        self.handle_editor_action(action);
+                    }
+                    Event::TextInput(character) if self.editor.open => {
+                        self.editor.type_character(character);
+                    }
                     Event::KeyPressed(keycode) => {
-                        if let KeyCode::Space = keycode {
-                            self.remove_floor();
+                        self.camera.handle_key(keycode, &self.config);
+                        if self.playback.is_some() {
+                            self.handle_playback_key(keycode);
+                        } else if let KeyCode::Space = keycode {
+                            self.start_drop();
                         }
                     }
-                    Event::IntersectionEvent(collider_handle1, collider_handle2) => {
-                        if let Some(_) = self.winner {
-                            continue;
-                        }
-
-                        let id = self
-                            .physics
-                            .get_id_by_collider_handle(collider_handle1)
-                            .unwrap();
-                        let winner = if let Some(name) = self.draw_data.get_name(id) {
-                            (
-                                helpers::create_winner_text::create_winner_text(context, name),
-                                self.draw_data.get_color(id),
-                            )
-                        } else {
-                            let id = self
-                                .physics
-                                .get_id_by_collider_handle(collider_handle2)
-                                .unwrap();
-                            (
-                                helpers::create_winner_text::create_winner_text(
-                                    context,
-                                    self.draw_data.get_name(id).unwrap(),
-                                ),
-                                self.draw_data.get_color(id),
-                            )
-                        };
-                        self.winner = Some(winner);
+                    Event::MouseClicked(MouseButton::Left)
+                    | Event::GamepadButtonPressed
+                    | Event::StartRequested
+                        if !self.editor.open =>
+                    {
+                        self.start_drop();
                     }
+                    Event::Intersection(collider_handle1, collider_handle2) => {
+                        self.simulation
+                            .handle_intersection(collider_handle1, collider_handle2);
+                    }
+                    _ => {}
                 }
             }
+            self.create_winner(context);
+            self.notify_observers();
+        }
+        if let Some(winner) = self.winner.as_mut() {
+            winner.update(timer::delta(context).as_secs_f32());
         }
+        if let Some(server) = &self.server {
+            server.update(&self.simulation);
+        }
+        let lowest_ball_position = self.lowest_ball_position();
+        self.camera.follow(lowest_ball_position, &self.config);
         Ok(())
     }
 
     fn draw(&mut self, context: &mut ggez::Context) -> ggez::GameResult {
         graphics::clear(context, self.config.background_color);
+        let (window_width, window_height) = graphics::drawable_size(context);
+        graphics::set_screen_coordinates(
+            context,
+            self.camera.visible_rect(window_width, window_height),
+        )?;
         let mut mesh_builder = MeshBuilder::new();
-        for (_handle, body) in self.physics.bodies.iter() {
+        let mut balls = vec![];
+        self.effects
+            .draw_trails(&mut mesh_builder, &mut self.simulation);
+        let choice_ids = self.simulation.choice_ids().to_vec();
+        let draw_data = &mut self.simulation.draw_data;
+        for (_handle, body) in self.simulation.physics.bodies.iter() {
             let position = body.world_com;
             let id = body.user_data;
-            match self.draw_data.get_type(id) {
+            match draw_data.get_type(id) {
                 DataType::Ball => {
-                    let color = self.draw_data.get_color(id);
+                    let color = draw_data.get_color(id);
+                    let radius = draw_data.get_radius(id);
+                    balls.push((id, position.x, position.y, radius));
                     mesh_builder
                         .circle(
                             DrawMode::fill(),
                             [position.x, position.y],
-                            self.config.choice_radius,
+                            radius,
                             0.1,
                             color,
                         )
                         .circle(
                             DrawMode::stroke(2.0),
                             [position.x, position.y],
-                            self.config.choice_radius,
+                            radius,
                             0.1,
                             BLACK,
                         );
+                    if self.config.accessibility {
+                        let pattern_color = if is_dark_color(&color) {
+                            graphics::WHITE
+                        } else {
+                            BLACK
+                        };
+                        let index = choice_ids
+                            .iter()
+                            .position(|choice_id| *choice_id == id)
+                            .unwrap_or(0);
+                        ball_patterns::draw_pattern(
+                            &mut mesh_builder,
+                            index,
+                            [position.x, position.y],
+                            radius,
+                            pattern_color,
+                        )?;
+                    }
                 }
                 DataType::Wall => {
-                    let color = self.draw_data.get_color(id);
-                    let rect = self.draw_data.get_rectangle(id);
+                    let color = draw_data.get_color(id);
+                    let rect = draw_data.get_rectangle(id);
                     mesh_builder.rectangle(DrawMode::fill(), rect, color);
                 }
                 DataType::Nail => {
@@ -332,8 +861,8 @@ impl EventHandler for MainState {
                     );
                 }
                 DataType::Collector => {
-                    let rect = self.draw_data.get_rectangle(id);
-                    let rotation = self.draw_data.get_rotation(id);
+                    let rect = draw_data.get_rectangle(id);
+                    let rotation = draw_data.get_rotation(id);
                     let mesh = MeshBuilder::new()
                         .rectangle(DrawMode::fill(), rect, self.config.wall_color)
                         .build(context)?;
@@ -343,35 +872,44 @@ impl EventHandler for MainState {
             }
         }
 
+        self.effects.draw_sparks(&mut mesh_builder);
         let mesh = mesh_builder.build(context)?;
         graphics::draw(context, &mesh, DrawParam::new())?;
-        if let Some((winner, color)) = &self.winner {
-            let (width, height) = graphics::drawable_size(context);
-            let (winner_width, winner_height) = winner.dimensions(context);
-            let mut background_color = if is_dark_color(color) {
-                self.config.winning_background_color_light
-            } else {
-                self.config.winning_background_color_dark
-            };
-            background_color.a = self.config.winning_background_color_alpha;
-
-            let background_mesh = MeshBuilder::new()
-                .rectangle(
-                    DrawMode::fill(),
-                    Rect::new(0.0, 0.0, width, height),
-                    background_color,
-                )
-                .build(context)?;
-            graphics::draw(context, &background_mesh, DrawParam::new())?;
+        self.draw_avatars(context)?;
+        self.draw_labels(context, &balls)?;
+        graphics::set_screen_coordinates(
+            context,
+            Rect::new(0.0, 0.0, window_width, window_height),
+        )?;
+        if self.editor.open {
+            self.editor.draw(context, &self.config)?;
+        }
+        if self.config.show_leaderboard {
+            let finished = self.simulation.finished().to_vec();
+            let racing =
+                leaderboard::racing_order(&self.simulation.physics.ball_tracker, &finished);
+            leaderboard::draw(
+                context,
+                &self.config,
+                &mut self.simulation.draw_data,
+                &finished,
+                &racing,
+            )?;
+        }
+        if let Some(winner) = &self.winner {
+            winner.draw(context, &self.config, self.winner_font)?;
+        }
+        self.draw_countdown(context)?;
+        self.effects.draw_confetti(context)?;
+        if let Some(playback) = &self.playback {
+            let mut status = Text::new(playback.status(self.simulation.current_step()));
+            status.set_font(Font::default(), Scale::uniform(24.0));
             graphics::draw(
                 context,
-                winner,
+                &status,
                 DrawParam::new()
-                    .dest([
-                        width / 2.0 - winner_width as f32 / 2.0,
-                        height / 2.0 - winner_height as f32 / 2.0,
-                    ])
-                    .color(*color),
+                    .dest([self.config.wall_width + 10.0, 10.0])
+                    .color(graphics::WHITE),
             )?;
         }
         graphics::present(context)
@@ -386,4 +924,89 @@ impl EventHandler for MainState {
     ) {
         self.send_events.send(Event::KeyPressed(keycode)).unwrap();
     }
+
+    fn text_input_event(&mut self, _context: &mut Context, character: char) {
+        self.send_events.send(Event::TextInput(character)).unwrap();
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _context: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        if let MouseButton::Right | MouseButton::Middle = button {
+            self.camera.dragging = true;
+        }
+        self.send_events.send(Event::MouseClicked(button)).unwrap();
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _context: &mut Context,
+        _button: Button,
+        _id: GamepadId,
+    ) {
+        self.send_events.send(Event::GamepadButtonPressed).unwrap();
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _context: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        if let MouseButton::Right | MouseButton::Middle = button {
+            self.camera.dragging = false;
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _context: &mut Context, _x: f32, _y: f32, dx: f32, dy: f32) {
+        if self.camera.dragging {
+            self.camera.pan(-dx, -dy);
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _context: &mut Context, _x: f32, y: f32) {
+        self.camera.zoom_by(1.1_f32.powf(y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Winners(Rc<RefCell<Vec<String>>>);
+
+    impl DropObserver for Winners {
+        fn winner_declared(&mut self, choice: &Choice) {
+            self.0.borrow_mut().push(choice.name.clone());
+        }
+    }
+
+    #[test]
+    fn restarting_with_fewer_choices_reports_the_old_winner_by_its_old_choice() {
+        let mut config = Config::default();
+        config.seed = Some(1);
+        let choices = vec![Choice::new("a"), Choice::new("b"), Choice::new("c")];
+        let mut main_state = MainState::with_choices(config, choices);
+        let winners = Rc::new(RefCell::new(vec![]));
+        main_state.add_observer(Box::new(Winners(winners.clone())));
+        main_state.simulation.setup(&main_state.choices);
+        let first_ball = main_state.simulation.choice_ids()[0];
+        main_state
+            .send_events
+            .send(Event::WinnerDeclared(first_ball))
+            .unwrap();
+
+        // The new board hands the first ball id to "b"
+        main_state.restart_round(vec![Choice::new("b"), Choice::new("c")]);
+
+        assert_eq!(*winners.borrow(), vec!["a".to_owned()]);
+    }
 }
@@ -1,15 +1,30 @@
-use eyre::Result;
-use ggez::conf::{self, WindowMode, WindowSetup};
-use ggez::{event, ContextBuilder};
+use std::process;
+
+use eyre::{bail, Result};
+use random_chooser_drop::config::config_struct::Config;
 use random_chooser_drop::config::load_config;
-use random_chooser_drop::MainState;
+use random_chooser_drop::{
+    convert_choices, export_drop, load_choices, run_headless, Choice, ChoiceFilter, ExportOptions,
+    MainState, Outcome,
+};
 
 const DEFAULT_CONFIG_FILE_PATH: &str = "config.json";
 const DEFAULT_CHOICES_PATH: &str = "choices.json";
 const DEFAULT_CHOICE_FILE_TYPE: &str = "json";
+const DEFAULT_EXPORT_STEP_INTERVAL: u64 = 2;
+const DEFAULT_EXPORT_SCALE: f32 = 0.5;
+const DEFAULT_EXPORT_HOLD_SECONDS: f32 = 3.0;
+const DEFAULT_OUTPUT: &str = "text";
+/// Exit code for closing the window before the drop was decided, so scripts can tell it apart
+/// from errors, which exit with 1.
+const EXIT_NO_WINNER: i32 = 2;
 
 fn main() -> Result<()> {
     let mut arguments = pico_args::Arguments::from_env();
+    if arguments.subcommand()?.as_deref() == Some("convert") {
+        return convert(arguments);
+    }
+
     let config_path = arguments
         .opt_value_from_str("--config")?
         .unwrap_or_else(|| DEFAULT_CONFIG_FILE_PATH.to_owned());
@@ -20,18 +35,170 @@ fn main() -> Result<()> {
         .opt_value_from_str("--file-type")?
         .unwrap_or_else(|| DEFAULT_CHOICE_FILE_TYPE.to_owned());
 
-    let mut config = load_config(config_path)?;
+    let mut config = load_config(config_path, arguments.opt_value_from_str("--theme")?)?;
     config.use_stdin = arguments.contains(["-s", "--stdin"]);
-    let window_mode = WindowMode::default().dimensions(config.width, config.height);
-    let window_setup = WindowSetup::default()
-        .title(&config.title)
-        .vsync(config.vsync);
-    let (mut context, mut event_loop) = ContextBuilder::new("random_chooser_drop", "Brookzerker")
-        .window_mode(window_mode)
-        .window_setup(window_setup)
-        .build()?;
-    let mut main_state = MainState::new(config, choices_path, choice_file_type)?;
-    main_state.setup();
-    event::run(&mut context, &mut event_loop, &mut main_state)?;
+    if let Some(seed) = arguments.opt_value_from_str("--seed")? {
+        config.seed = Some(seed);
+    }
+    if arguments.contains("--accessible") {
+        config.accessibility = true;
+    }
+    if arguments.contains("--auto-start") {
+        config.auto_start = true;
+    }
+    if let Some(control_port) = arguments.opt_value_from_str("--serve")? {
+        config.control_port = Some(control_port);
+    }
+    config
+        .include_tags
+        .extend(arguments.values_from_str::<_, String>("--include-tag")?);
+    config
+        .exclude_tags
+        .extend(arguments.values_from_str::<_, String>("--exclude-tag")?);
+    config
+        .exclude
+        .extend(arguments.values_from_str::<_, String>("--exclude")?);
+    if let Some(absent_file) = arguments.opt_value_from_str("--absent")? {
+        config.absent_file = Some(absent_file);
+    }
+    let output: String = arguments
+        .opt_value_from_str("--output")?
+        .unwrap_or_else(|| DEFAULT_OUTPUT.to_owned());
+    if output != "text" && output != "json" {
+        bail!("--output must be text or json");
+    }
+    let default_drop_at_step = if config.auto_start {
+        config.countdown_steps()
+    } else {
+        0
+    };
+
+    if let Some(export_path) = arguments.opt_value_from_str("--export")? {
+        let drop_at_step = arguments
+            .opt_value_from_str("--drop-at-step")?
+            .unwrap_or(default_drop_at_step);
+        let step_interval = arguments
+            .opt_value_from_str("--export-every")?
+            .unwrap_or(DEFAULT_EXPORT_STEP_INTERVAL);
+        if step_interval == 0 {
+            bail!("--export-every must be at least 1");
+        }
+        let options = ExportOptions {
+            path: export_path,
+            step_interval,
+            scale: arguments
+                .opt_value_from_str("--export-scale")?
+                .unwrap_or(DEFAULT_EXPORT_SCALE),
+            font_path: arguments.opt_value_from_str("--font")?,
+            hold_seconds: DEFAULT_EXPORT_HOLD_SECONDS,
+        };
+        let (choices, filter) = load_filtered_choices(&config, choices_path, choice_file_type)?;
+        let outcome = export_drop(config, filter.apply(&choices), drop_at_step, options)?;
+        return print_outcome(&outcome, &output);
+    }
+
+    if arguments.contains("--headless") {
+        let drop_at_step = arguments
+            .opt_value_from_str("--drop-at-step")?
+            .unwrap_or(default_drop_at_step);
+        let (choices, filter) = load_filtered_choices(&config, choices_path, choice_file_type)?;
+        let outcome = run_headless(config, filter.apply(&choices), drop_at_step)?;
+        return print_outcome(&outcome, &output);
+    }
+
+    let mute = arguments.contains("--mute");
+    let replay_path: Option<String> = arguments.opt_value_from_str("--replay")?;
+    let record_path: Option<String> = arguments.opt_value_from_str("--record")?;
+    let mut main_state = if let Some(replay_path) = replay_path {
+        MainState::from_recording(replay_path)?
+    } else {
+        let use_stdin = config.use_stdin;
+        let (choices, filter) =
+            load_filtered_choices(&config, choices_path.clone(), choice_file_type.clone())?;
+        let mut main_state = MainState::new(config, choices, &filter);
+        if !use_stdin {
+            main_state.save_choices_to(choices_path, choice_file_type);
+        }
+        main_state
+    };
+    if let Some(record_path) = record_path {
+        main_state.record_to(record_path);
+    }
+    if mute {
+        main_state.mute();
+    }
+    if output == "json" {
+        main_state.quiet_stdout();
+    }
+
+    match main_state.run()? {
+        Some(outcome) => print_outcome(&outcome, &output),
+        None => {
+            eprintln!("The window was closed before a winner was decided");
+            process::exit(EXIT_NO_WINNER);
+        }
+    }
+}
+
+/// Loads the choices and tells the user what the filters left out.
+fn load_filtered_choices(
+    config: &Config,
+    choices_path: String,
+    choice_file_type: String,
+) -> Result<(Vec<Choice>, ChoiceFilter)> {
+    let (choices, filter) = load_choices(config, choices_path, choice_file_type)?;
+    for name in filter.unknown_names.iter() {
+        eprintln!("No choice named {} to leave out", name);
+    }
+    let left_out = filter.left_out();
+    if left_out > 0 {
+        eprintln!("Left out {} of {} choices", left_out, choices.len());
+    }
+    Ok((choices, filter))
+}
+
+/// `convert <input> <output> [--from <type>] [--to <type>]`
+fn convert(mut arguments: pico_args::Arguments) -> Result<()> {
+    let input_type = arguments.opt_value_from_str("--from")?;
+    let output_type = arguments.opt_value_from_str("--to")?;
+    let input_path: String = arguments.free_from_str()?;
+    let output_path: String = arguments.free_from_str()?;
+    let count = convert_choices(
+        input_path.clone(),
+        input_type,
+        output_path.clone(),
+        output_type,
+    )?;
+    eprintln!(
+        "Converted {} choices from {} to {}",
+        count, input_path, output_path
+    );
+    Ok(())
+}
+
+fn print_outcome(outcome: &Outcome, output: &str) -> Result<()> {
+    if output == "json" {
+        println!("{}", serde_json::to_string(outcome)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} won after {} steps (seed {})",
+        outcome.winner, outcome.steps, outcome.seed
+    );
+    if outcome.nudges > 0 {
+        println!(
+            "The balls got stuck and were nudged {} times",
+            outcome.nudges
+        );
+    }
+    if outcome.tiebreak {
+        println!("No ball reached the finish, the lowest ball won");
+    }
+    if outcome.ranking.len() > 1 {
+        for (rank, name) in outcome.ranking.iter().enumerate() {
+            println!("{}. {}", rank + 1, name);
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,27 @@
+use crate::choices::Choice;
+
+/// Callbacks for the moments of a drop that tooling usually cares about. Register one with
+/// `MainState::add_observer`; every method has an empty default so only the interesting ones
+/// need implementing.
+pub trait DropObserver {
+    /// The balls have been placed on the board. Called again when the board is rebuilt, for
+    /// example when scrubbing back through a replay.
+    fn round_started(&mut self, _choices: &[Choice]) {}
+
+    /// The floor was removed after `step` simulation steps.
+    fn floor_removed(&mut self, _step: u64) {}
+
+    /// A ball crossed the finish. `rank` starts at 1.
+    fn ball_finished(&mut self, _choice: &Choice, _rank: usize) {}
+
+    /// The ball is one of those that stopped moving, or that hadn't finished when the drop
+    /// timed out. The balls are nudged or the lowest one wins a tiebreak straight afterwards.
+    fn ball_stuck(&mut self, _choice: &Choice) {}
+
+    /// The drop has been decided, either by the first ball to finish or by a tiebreak.
+    fn winner_declared(&mut self, _choice: &Choice) {}
+
+    /// The window was closed. Observers doing work in the background should finish it before
+    /// returning, as the process may exit straight afterwards.
+    fn finished(&mut self) {}
+}
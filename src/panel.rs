@@ -0,0 +1,83 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, MeshBuilder, Rect, Scale, Text};
+use ggez::{Context, GameResult};
+
+use crate::config::config_struct::Config;
+use crate::helpers::is_dark_color::is_dark_color;
+
+const FONT_SIZE: f32 = 18.0;
+pub const ROW_HEIGHT: f32 = 24.0;
+pub const PADDING: f32 = 10.0;
+pub const SWATCH_SIZE: f32 = 14.0;
+
+/// Draws the background of a full-height panel at `x`, in screen coordinates, and returns the
+/// colour text should be drawn in on top of it. The background is whichever winner background
+/// stands out against the board, at `alpha`.
+pub fn draw_background(
+    context: &mut Context,
+    config: &Config,
+    x: f32,
+    width: f32,
+    alpha: f32,
+) -> GameResult<Color> {
+    let (_window_width, window_height) = graphics::drawable_size(context);
+    let mut background_color = if is_dark_color(&config.background_color) {
+        config.winning_background_color_dark
+    } else {
+        config.winning_background_color_light
+    };
+    background_color.a = alpha;
+
+    let panel = MeshBuilder::new()
+        .rectangle(
+            DrawMode::fill(),
+            Rect::new(x, 0.0, width, window_height),
+            background_color,
+        )
+        .build(context)?;
+    graphics::draw(context, &panel, DrawParam::new())?;
+
+    if is_dark_color(&background_color) {
+        Ok(graphics::WHITE)
+    } else {
+        Ok(graphics::BLACK)
+    }
+}
+
+pub fn draw_text(context: &mut Context, text: &str, x: f32, y: f32, color: Color) -> GameResult {
+    let mut text = Text::new(text);
+    text.set_font(Font::default(), Scale::uniform(FONT_SIZE));
+    graphics::draw(context, &text, DrawParam::new().dest([x, y]).color(color))
+}
+
+/// A line of text starting at `x`, after a colour swatch when `swatch` is given.
+pub fn draw_row(
+    context: &mut Context,
+    swatch: Option<Color>,
+    text: &str,
+    x: f32,
+    y: f32,
+    color: Color,
+) -> GameResult {
+    let mut text_x = x;
+    if let Some(swatch) = swatch {
+        draw_swatch(context, swatch, x, y)?;
+        text_x += SWATCH_SIZE + PADDING;
+    }
+    draw_text(context, text, text_x, y, color)
+}
+
+pub fn draw_swatch(context: &mut Context, color: Color, x: f32, y: f32) -> GameResult {
+    let swatch = MeshBuilder::new()
+        .rectangle(
+            DrawMode::fill(),
+            Rect::new(x, y + 2.0, SWATCH_SIZE, SWATCH_SIZE),
+            color,
+        )
+        .rectangle(
+            DrawMode::stroke(1.0),
+            Rect::new(x, y + 2.0, SWATCH_SIZE, SWATCH_SIZE),
+            graphics::BLACK,
+        )
+        .build(context)?;
+    graphics::draw(context, &swatch, DrawParam::new())
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use rapier2d::dynamics::RigidBodySet;
+
+#[derive(Default)]
+pub struct BallTracker {
+    balls: BTreeMap<u128, TrackedBall>,
+    /// Balls resting on the floor before the drop aren't stuck, so idle steps are only
+    /// counted once `start_counting_idle` has been called.
+    counting_idle: bool,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct TrackedBall {
+    pub x: f32,
+    pub y: f32,
+    pub speed: f32,
+    pub idle_steps: u32,
+}
+
+impl BallTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&mut self, id: u128) {
+        self.balls.insert(id, TrackedBall::default());
+    }
+
+    pub fn update(&mut self, bodies: &RigidBodySet, idle_velocity_threshold: f32) {
+        for (_handle, body) in bodies.iter() {
+            let tracked_ball = if let Some(tracked_ball) = self.balls.get_mut(&body.user_data) {
+                tracked_ball
+            } else {
+                continue;
+            };
+
+            tracked_ball.x = body.world_com.x;
+            tracked_ball.y = body.world_com.y;
+            tracked_ball.speed = body.linvel().norm();
+            if self.counting_idle && tracked_ball.speed < idle_velocity_threshold {
+                tracked_ball.idle_steps += 1;
+            } else {
+                tracked_ball.idle_steps = 0;
+            }
+        }
+    }
+
+    /// How many steps every tracked ball has been idle for, which is the idle time of the
+    /// ball that moved most recently.
+    pub fn all_idle_steps(&self) -> u32 {
+        self.balls
+            .values()
+            .map(|tracked_ball| tracked_ball.idle_steps)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// The ball closest to the finish. Y grows downwards so this is the largest y.
+    pub fn lowest(&self) -> Option<u128> {
+        self.balls
+            .iter()
+            .max_by(|(_, a), (_, b)| a.y.partial_cmp(&b.y).unwrap())
+            .map(|(id, _)| *id)
+    }
+
+    pub fn get(&self, id: u128) -> Option<&TrackedBall> {
+        self.balls.get(&id)
+    }
+
+    pub fn ids(&self) -> Vec<u128> {
+        self.balls.keys().copied().collect()
+    }
+
+    pub fn start_counting_idle(&mut self) {
+        self.counting_idle = true;
+        self.reset_idle();
+    }
+
+    pub fn reset_idle(&mut self) {
+        for tracked_ball in self.balls.values_mut() {
+            tracked_ball.idle_steps = 0;
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crossbeam::channel::Sender;
+use rapier2d::geometry::{ColliderHandle, ContactEvent};
 use rapier2d::pipeline::EventHandler;
 
 use crate::event_manager::event::Event;
@@ -6,12 +7,18 @@ use crate::event_manager::EventManager;
 
 pub struct PhysicsEventHandler {
     event_sender: Sender<Event>,
+    /// Contacts go back to `Physics` first, which adds the impact speed before sending them on.
+    contact_sender: Sender<(ColliderHandle, ColliderHandle)>,
 }
 
 impl PhysicsEventHandler {
-    pub fn new(event_manager: &mut EventManager) -> Self {
+    pub fn new(
+        event_manager: &mut EventManager,
+        contact_sender: Sender<(ColliderHandle, ColliderHandle)>,
+    ) -> Self {
         Self {
             event_sender: event_manager.get_sender(),
+            contact_sender,
         }
     }
 }
@@ -20,10 +27,14 @@ impl EventHandler for PhysicsEventHandler {
     fn handle_intersection_event(&self, event: rapier2d::geometry::IntersectionEvent) {
         if event.intersecting {
             self.event_sender
-                .send(Event::IntersectionEvent(event.collider1, event.collider2))
+                .send(Event::Intersection(event.collider1, event.collider2))
                 .unwrap();
         }
     }
 
-    fn handle_contact_event(&self, _event: rapier2d::geometry::ContactEvent) {}
+    fn handle_contact_event(&self, event: rapier2d::geometry::ContactEvent) {
+        if let ContactEvent::Started(collider1, collider2) = event {
+            self.contact_sender.send((collider1, collider2)).unwrap();
+        }
+    }
 }
@@ -1,4 +1,6 @@
+use crossbeam::channel::{Receiver, Sender};
 use nalgebra::Isometry2;
+use rand::Rng;
 use rapier2d::dynamics::{
     CCDSolver, IntegrationParameters, JointSet, RigidBodyBuilder, RigidBodyHandle, RigidBodySet,
 };
@@ -6,11 +8,14 @@ use rapier2d::geometry::{BroadPhase, ColliderBuilder, ColliderHandle, ColliderSe
 use rapier2d::pipeline::PhysicsPipeline;
 
 use crate::config::config_struct::Config;
+use crate::event_manager::event::Event;
 use crate::event_manager::EventManager;
 use crate::helpers::vector2::Vector2;
 
+use self::ball_tracker::BallTracker;
 use self::event_handler::PhysicsEventHandler;
 
+pub mod ball_tracker;
 pub mod event_handler;
 
 pub struct Physics {
@@ -25,14 +30,22 @@ pub struct Physics {
     ccd_solver: CCDSolver,
     last_used_id: u128,
     event_handler: PhysicsEventHandler,
+    contacts: Receiver<(ColliderHandle, ColliderHandle)>,
+    event_sender: Sender<Event>,
+    pub ball_tracker: BallTracker,
+    idle_velocity_threshold: f32,
 }
 
 impl Physics {
     pub fn new(config: &Config, event_manager: &mut EventManager) -> Self {
+        let (contact_sender, contacts) = crossbeam::channel::unbounded();
         Self {
             pipeline: PhysicsPipeline::new(),
             gravity: Vector2::new(0.0, config.gravity),
-            integration_parameters: IntegrationParameters::default(),
+            integration_parameters: IntegrationParameters {
+                dt: 1.0 / config.steps_per_second as f32,
+                ..IntegrationParameters::default()
+            },
             broad_phase: BroadPhase::new(),
             narrow_phase: NarrowPhase::new(),
             bodies: RigidBodySet::new(),
@@ -40,11 +53,22 @@ impl Physics {
             joints: JointSet::new(),
             ccd_solver: CCDSolver::new(),
             last_used_id: 0,
-            event_handler: PhysicsEventHandler::new(event_manager),
+            event_handler: PhysicsEventHandler::new(event_manager, contact_sender),
+            contacts,
+            event_sender: event_manager.get_sender(),
+            ball_tracker: BallTracker::new(),
+            idle_velocity_threshold: config.idle_velocity_threshold,
         }
     }
 
-    pub fn insert_ball(&mut self, position: Vector2, radius: f32, restitution: f32) -> u128 {
+    pub fn insert_ball(
+        &mut self,
+        position: Vector2,
+        radius: f32,
+        restitution: f32,
+        density: f32,
+        friction: f32,
+    ) -> u128 {
         let id = self.last_used_id + 1;
         let ball = RigidBodyBuilder::new_dynamic()
             .position(Isometry2::new(position.to_nalgebra(), 0.0))
@@ -53,10 +77,13 @@ impl Physics {
         let body_handle = self.bodies.insert(ball);
         let collider = ColliderBuilder::ball(radius)
             .restitution(restitution)
+            .density(density)
+            .friction(friction)
             .user_data(id)
             .build();
         self.colliders
             .insert(collider, body_handle, &mut self.bodies);
+        self.ball_tracker.track(id);
 
         self.last_used_id = id;
         id
@@ -152,6 +179,35 @@ impl Physics {
             &hooks,
             &self.event_handler,
         );
+        // The tracker still holds the speeds from before this step, so before any bounce
+        while let Ok((collider1, collider2)) = self.contacts.try_recv() {
+            let impact_speed = [collider1, collider2]
+                .iter()
+                .filter_map(|collider| self.get_id_by_collider_handle(*collider))
+                .filter_map(|id| self.ball_tracker.get(id))
+                .map(|ball| ball.speed)
+                .fold(0.0, f32::max);
+            self.event_sender
+                .send(Event::Contact(collider1, collider2, impact_speed))
+                .unwrap();
+        }
+        self.ball_tracker
+            .update(&self.bodies, self.idle_velocity_threshold);
+    }
+
+    /// Knock every ball upwards and a little to the side so that balls resting on a nail or
+    /// wedged in a corner get moving again.
+    pub fn nudge_balls(&mut self, rng: &mut impl Rng, speed: f32) {
+        let ball_ids = self.ball_tracker.ids();
+        for (_handle, body) in self.bodies.iter_mut() {
+            if !ball_ids.contains(&body.user_data) {
+                continue;
+            }
+
+            let direction = nalgebra::Vector2::new(rng.gen_range(-1.0..1.0), -1.0).normalize();
+            body.apply_impulse(direction * speed * body.mass(), true);
+        }
+        self.ball_tracker.reset_idle();
     }
 
     pub fn remove(&mut self, handle: RigidBodyHandle) {
@@ -169,6 +225,16 @@ impl Physics {
         None
     }
 
+    pub fn get_position_by_collider_handle(
+        &self,
+        collider_handle: ColliderHandle,
+    ) -> Option<(f32, f32)> {
+        self.colliders.get(collider_handle).map(|collider| {
+            let translation = collider.position().translation;
+            (translation.x, translation.y)
+        })
+    }
+
     pub fn get_id_by_collider_handle(&self, collider_handle: ColliderHandle) -> Option<u128> {
         self.colliders
             .get(collider_handle)
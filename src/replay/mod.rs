@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::choices::Choice;
+use crate::config::config_struct::Config;
+
+/// Everything needed to run a drop again. The simulation is deterministic, so instead of ball
+/// positions we only keep the seed, the board, the choices and the step the floor was removed at.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Recording {
+    pub seed: u64,
+    pub config: Config,
+    pub choices: Vec<Choice>,
+    pub floor_removed_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub winner: Option<String>,
+}
+
+impl Recording {
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut recording_json = String::new();
+        file.read_to_string(&mut recording_json)?;
+        let recording: Recording = serde_json::from_str(&recording_json)?;
+        Ok(recording)
+    }
+}
+
+const SLOW_MOTION_FACTORS: [u32; 4] = [1, 2, 4, 8];
+
+pub struct Playback {
+    pub recording: Recording,
+    pub paused: bool,
+    slow_motion_index: usize,
+    tick: u32,
+    /// Set when the replay picked a different winner than the recording, which means the
+    /// simulation is no longer deterministic across versions or platforms.
+    mismatch: Option<String>,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            paused: false,
+            slow_motion_index: 0,
+            tick: 0,
+            mismatch: None,
+        }
+    }
+
+    /// Whether the simulation should step on this update tick. Slow motion only steps every
+    /// few ticks so the simulation itself still runs with the recorded `dt`.
+    pub fn should_step(&mut self) -> bool {
+        if self.paused {
+            return false;
+        }
+
+        self.tick += 1;
+        self.tick % self.slow_motion_factor() == 0
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn cycle_slow_motion(&mut self) {
+        self.slow_motion_index = (self.slow_motion_index + 1) % SLOW_MOTION_FACTORS.len();
+        self.tick = 0;
+    }
+
+    pub fn slow_motion_factor(&self) -> u32 {
+        SLOW_MOTION_FACTORS[self.slow_motion_index]
+    }
+
+    /// Compares the replayed winner against the recorded one. Recordings made before the winner
+    /// was saved have nothing to compare against.
+    pub fn verify_winner(&mut self, winner: &str) {
+        let recorded = match &self.recording.winner {
+            Some(recorded) if recorded != winner => recorded,
+            _ => return,
+        };
+        let mismatch = format!(
+            "the recording was won by {} but the replay by {}",
+            recorded, winner
+        );
+        eprintln!("Replay doesn't match: {}", mismatch);
+        self.mismatch = Some(mismatch);
+    }
+
+    pub fn status(&self, step: u64) -> String {
+        let mut status = format!("Replay - step {}", step);
+        if self.slow_motion_factor() > 1 {
+            status.push_str(&format!(" - 1/{} speed", self.slow_motion_factor()));
+        }
+        if self.paused {
+            status.push_str(" - paused");
+        }
+        if let Some(mismatch) = &self.mismatch {
+            status.push_str(&format!(" - MISMATCH: {}", mismatch));
+        }
+        status.push_str("   [P] pause  [S] slow motion  [,/.] scrub  [R] restart");
+        status
+    }
+}
@@ -0,0 +1,201 @@
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crossbeam::channel::{Receiver, Sender};
+use eyre::{eyre, Result};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::Message;
+
+use crate::choices::{parse_choices_csv, parse_choices_json, Choice};
+use crate::event_manager::event::{Event, EventKind};
+use crate::event_manager::EventManager;
+use crate::simulation::{Outcome, Simulation};
+
+/// Things the control server asks `MainState` to do that don't fit in an `Event`.
+pub enum ServerCommand {
+    LoadChoices(Vec<Choice>),
+}
+
+type Clients = Arc<Mutex<Vec<Sender<String>>>>;
+
+/// Lets another program drive the chooser over localhost.
+///
+/// HTTP on `port`:
+/// - `POST /choices` replaces the choices with a JSON array, or CSV when the request has a
+///   `Content-Type` of `text/csv`, and starts a new round.
+/// - `POST /drop` starts the drop, the same as pressing Space.
+/// - `GET /outcome` returns the outcome as JSON, or 202 while the drop is undecided.
+///
+/// A WebSocket on `port + 1` streams ball positions every frame and the drop's events.
+pub struct ControlServer {
+    commands: Receiver<ServerCommand>,
+    outcome: Arc<Mutex<Option<Outcome>>>,
+    clients: Clients,
+    events: Receiver<Event>,
+}
+
+impl ControlServer {
+    pub fn start(port: u16, event_manager: &mut EventManager) -> Result<Self> {
+        let websocket_port = port.checked_add(1).ok_or_else(|| {
+            eyre!("the control port must leave room for the WebSocket port after it")
+        })?;
+        let server = Server::http(("127.0.0.1", port)).map_err(|error| eyre!("{}", error))?;
+        let websocket_listener = TcpListener::bind(("127.0.0.1", websocket_port))?;
+        let (command_sender, commands) = crossbeam::channel::unbounded();
+        let outcome = Arc::new(Mutex::new(None));
+        let clients: Clients = Arc::new(Mutex::new(vec![]));
+
+        let event_sender = event_manager.get_sender();
+        let http_outcome = outcome.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &command_sender, &event_sender, &http_outcome);
+            }
+        });
+
+        let websocket_clients = clients.clone();
+        thread::spawn(move || {
+            for stream in websocket_listener
+                .incoming()
+                .filter_map(|stream| stream.ok())
+            {
+                let (sender, receiver) = crossbeam::channel::unbounded::<String>();
+                websocket_clients.lock().unwrap().push(sender);
+                thread::spawn(move || {
+                    let mut websocket = match tungstenite::accept(stream) {
+                        Ok(websocket) => websocket,
+                        Err(_) => return,
+                    };
+                    for message in receiver {
+                        if websocket.write_message(Message::Text(message)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        eprintln!(
+            "Control server listening on http://127.0.0.1:{} and ws://127.0.0.1:{}",
+            port, websocket_port
+        );
+        Ok(Self {
+            commands,
+            outcome,
+            clients,
+            events: event_manager.subscribe_many(&[
+                EventKind::RoundReset,
+                EventKind::FloorRemoved,
+                EventKind::BallFinished,
+                EventKind::BallStuck,
+                EventKind::WinnerDeclared,
+            ]),
+        })
+    }
+
+    pub fn try_command(&self) -> Option<ServerCommand> {
+        self.commands.try_recv().ok()
+    }
+
+    /// Sends the drop's events and the current ball positions to every WebSocket client and
+    /// keeps the outcome served over HTTP up to date.
+    pub fn update(&self, simulation: &Simulation) {
+        let name = |id: u128| simulation.draw_data.get_name(id).cloned();
+        while let Ok(event) = self.events.try_recv() {
+            let message = match event {
+                Event::RoundReset => json!({ "type": "round_reset" }),
+                Event::FloorRemoved(step) => json!({ "type": "floor_removed", "step": step }),
+                Event::BallFinished(id, rank) => {
+                    json!({ "type": "ball_finished", "name": name(id), "rank": rank })
+                }
+                Event::BallStuck(id) => json!({ "type": "ball_stuck", "name": name(id) }),
+                Event::WinnerDeclared(id) => json!({ "type": "winner_declared", "name": name(id) }),
+                _ => continue,
+            };
+            self.broadcast(message.to_string());
+        }
+
+        let ball_tracker = &simulation.physics.ball_tracker;
+        let balls: Vec<_> = ball_tracker
+            .ids()
+            .into_iter()
+            .filter_map(|id| {
+                let ball = ball_tracker.get(id)?;
+                Some(json!({ "name": name(id), "x": ball.x, "y": ball.y }))
+            })
+            .collect();
+        self.broadcast(
+            json!({ "type": "positions", "step": simulation.current_step(), "balls": balls })
+                .to_string(),
+        );
+
+        *self.outcome.lock().unwrap() = simulation.outcome();
+    }
+
+    /// Clients that disconnected are dropped the next time something is sent.
+    fn broadcast(&self, message: String) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(message.clone()).is_ok());
+    }
+}
+
+fn handle_request(
+    mut request: Request,
+    commands: &Sender<ServerCommand>,
+    events: &Sender<Event>,
+    outcome: &Mutex<Option<Outcome>>,
+) {
+    let method = request.method().clone();
+    let url = request.url().to_owned();
+    let response = match (method, url.as_str()) {
+        (Method::Post, "/choices") => {
+            let is_csv = request.headers().iter().any(|header| {
+                header.field.equiv("Content-Type") && header.value.as_str().starts_with("text/csv")
+            });
+            let mut body = String::new();
+            let choices = request
+                .as_reader()
+                .read_to_string(&mut body)
+                .map_err(eyre::Report::from)
+                .and_then(|_| {
+                    if is_csv {
+                        parse_choices_csv(body.as_bytes())
+                    } else {
+                        parse_choices_json(&body)
+                    }
+                });
+            match choices {
+                Ok(choices) => {
+                    let count = choices.len();
+                    commands.send(ServerCommand::LoadChoices(choices)).unwrap();
+                    json_response(200, json!({ "choices": count }))
+                }
+                Err(error) => json_response(400, json!({ "error": error.to_string() })),
+            }
+        }
+        (Method::Post, "/drop") => {
+            events.send(Event::StartRequested).unwrap();
+            json_response(202, json!({ "status": "starting" }))
+        }
+        (Method::Get, "/outcome") => match &*outcome.lock().unwrap() {
+            Some(outcome) => json_response(200, json!(outcome)),
+            None => json_response(202, json!({ "status": "pending" })),
+        },
+        _ => json_response(404, json!({ "error": "not found" })),
+    };
+
+    if let Err(error) = request.respond(response) {
+        eprintln!("Could not answer a control server request: {}", error);
+    }
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type)
+}
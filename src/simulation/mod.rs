@@ -0,0 +1,538 @@
+use crossbeam::channel::{Receiver, Sender};
+use eyre::{bail, Result};
+use ggez::graphics::Rect;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rapier2d::geometry::ColliderHandle;
+use serde::Serialize;
+
+use crate::choices::Choice;
+use crate::config::config_struct::{Config, StuckAction};
+use crate::draw_data::{DataType, DrawData};
+use crate::event_manager::event::Event;
+use crate::event_manager::EventManager;
+use crate::helpers::create_winner_text::format_winner_message;
+use crate::helpers::palette::default_choice_color;
+use crate::helpers::vector2::Vector2;
+use crate::physics::Physics;
+
+/// The board, its balls and everything that decides a winner, stepped with a fixed `dt` and a
+/// seeded RNG. Nothing in here knows about ggez timing, so the same seed and the same inputs
+/// produce the same drop whether it is running in the window or headless.
+pub struct Simulation {
+    config: Config,
+    pub physics: Physics,
+    pub draw_data: DrawData,
+    floor_id: Option<u128>,
+    rng: ChaCha8Rng,
+    seed: u64,
+    step: u64,
+    floor_removed_at: Option<u64>,
+    nudges: u32,
+    winner: Option<u128>,
+    decided_at: Option<u64>,
+    finished: Vec<u128>,
+    choice_ids: Vec<u128>,
+    event_sender: Sender<Event>,
+}
+
+/// How a finished drop turned out.
+#[derive(Debug, Clone, Serialize)]
+pub struct Outcome {
+    /// The name of the winning choice.
+    pub winner: String,
+    /// Every choice that finished, in finishing order, starting with the winner.
+    pub ranking: Vec<String>,
+    /// How many simulation steps it took to decide the winner.
+    pub steps: u64,
+    pub seed: u64,
+    /// Every ball got stuck and the winner was picked for being the lowest, rather than for
+    /// reaching the finish first.
+    pub tiebreak: bool,
+    /// How many times stuck balls were knocked loose.
+    pub nudges: u32,
+}
+
+impl Simulation {
+    pub fn new(config: &Config, event_manager: &mut EventManager) -> Self {
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+        Self {
+            config: config.clone(),
+            physics: Physics::new(config, event_manager),
+            draw_data: DrawData::new(),
+            floor_id: None,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            seed,
+            step: 0,
+            floor_removed_at: None,
+            nudges: 0,
+            winner: None,
+            decided_at: None,
+            finished: vec![],
+            choice_ids: vec![],
+            event_sender: event_manager.get_sender(),
+        }
+    }
+
+    pub fn setup(&mut self, choices: &[Choice]) {
+        self.create_choice_balls(choices);
+        self.insert_floor();
+        self.create_nails();
+        self.create_walls();
+        self.create_collector(false);
+        self.create_collector(true);
+        self.create_winning_sensor();
+    }
+
+    pub fn step(&mut self) {
+        self.physics.update();
+        self.step += 1;
+        self.check_for_stuck_balls();
+    }
+
+    pub fn current_step(&self) -> u64 {
+        self.step
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The ball for each choice passed to `setup`, in the same order.
+    pub fn choice_ids(&self) -> &[u128] {
+        &self.choice_ids
+    }
+
+    /// Where the choice behind a ball sits in the list passed to `setup`.
+    pub fn choice_index(&self, id: u128) -> Option<usize> {
+        self.choice_ids
+            .iter()
+            .position(|choice_id| *choice_id == id)
+    }
+
+    /// The 1-based finishing position of a ball, if it has finished.
+    pub fn rank(&self, id: u128) -> Option<usize> {
+        self.finished
+            .iter()
+            .position(|finished_id| *finished_id == id)
+            .map(|index| index + 1)
+    }
+
+    pub fn floor_removed_at(&self) -> Option<u64> {
+        self.floor_removed_at
+    }
+
+    pub fn winner(&self) -> Option<u128> {
+        self.winner
+    }
+
+    pub fn remove_floor(&mut self) {
+        let floor_id = if let Some(id) = self.floor_id {
+            id
+        } else {
+            return;
+        };
+
+        let handle = self.physics.get_rigid_body_handle(floor_id).unwrap();
+        self.physics.remove(handle);
+        self.floor_id = None;
+        self.floor_removed_at = Some(self.step);
+        self.physics.ball_tracker.start_counting_idle();
+        self.send(Event::FloorRemoved(self.step));
+    }
+
+    pub fn handle_intersection(
+        &mut self,
+        collider_handle1: ColliderHandle,
+        collider_handle2: ColliderHandle,
+    ) {
+        let id = self
+            .physics
+            .get_id_by_collider_handle(collider_handle1)
+            .unwrap();
+        let id = if self.draw_data.get_name(id).is_some() {
+            id
+        } else {
+            self.physics
+                .get_id_by_collider_handle(collider_handle2)
+                .unwrap()
+        };
+
+        if !self.finished.contains(&id) {
+            self.finished.push(id);
+            self.send(Event::BallFinished(id, self.finished.len()));
+        }
+        if self.winner.is_none() {
+            self.declare_winner(id);
+        }
+    }
+
+    fn declare_winner(&mut self, id: u128) {
+        self.winner = Some(id);
+        self.decided_at = Some(self.step);
+        self.send(Event::WinnerDeclared(id));
+    }
+
+    fn send(&self, event: Event) {
+        self.event_sender.send(event).unwrap();
+    }
+
+    /// Balls that have crossed the finish sensor, in the order they crossed it.
+    pub fn finished(&self) -> &[u128] {
+        &self.finished
+    }
+
+    /// The configured winner message with the winner's details filled in.
+    pub fn winner_message(&self, choices: &[Choice]) -> Option<String> {
+        let id = self.winner?;
+        let group = self
+            .choice_index(id)
+            .and_then(|index| choices[index].group.as_deref())
+            .unwrap_or("");
+        Some(format_winner_message(
+            &self.config.winner_message,
+            self.draw_data.get_name(id)?,
+            self.rank(id).unwrap_or(1),
+            group,
+        ))
+    }
+
+    /// Steps the simulation without a window until a winner is decided, removing the floor
+    /// once `drop_at_step` is reached.
+    pub fn run_headless(
+        &mut self,
+        event_manager: &mut EventManager,
+        events: &Receiver<Event>,
+        drop_at_step: u64,
+    ) -> Result<Outcome> {
+        while self.winner.is_none() {
+            self.advance(event_manager, events, drop_at_step)?;
+        }
+
+        Ok(self.outcome().unwrap())
+    }
+
+    /// A single headless step, including removing the floor at `drop_at_step` and handling
+    /// the sensor events the step produced. Fails when there are no balls, as a headless drop
+    /// without any would never be decided.
+    pub fn advance(
+        &mut self,
+        event_manager: &mut EventManager,
+        events: &Receiver<Event>,
+        drop_at_step: u64,
+    ) -> Result<()> {
+        if self.choice_ids.is_empty() {
+            bail!("there must be at least one choice to drop");
+        }
+        if self.step >= drop_at_step {
+            self.remove_floor();
+        }
+        self.step();
+        event_manager.update()?;
+
+        while let Ok(event) = events.try_recv() {
+            if let Event::Intersection(collider_handle1, collider_handle2) = event {
+                self.handle_intersection(collider_handle1, collider_handle2);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        let winner = self.winner?;
+        // A tiebreak winner never crossed the finish, so it isn't in `finished`
+        let mut ranking = vec![winner];
+        ranking.extend(self.finished.iter().filter(|id| **id != winner));
+        Some(Outcome {
+            winner: self.draw_data.get_name(winner).unwrap().clone(),
+            ranking: ranking
+                .into_iter()
+                .map(|id| self.draw_data.get_name(id).unwrap().clone())
+                .collect(),
+            steps: self.decided_at.unwrap_or(self.step),
+            seed: self.seed,
+            tiebreak: !self.finished.contains(&winner),
+            nudges: self.nudges,
+        })
+    }
+
+    /// Balls can come to rest on a nail or in a corner of the collectors, so once the floor is
+    /// gone we watch for every ball sitting still and for the drop taking too long overall.
+    fn check_for_stuck_balls(&mut self) {
+        if self.winner.is_some() {
+            return;
+        }
+
+        let steps_since_drop = if let Some(floor_removed_at) = self.floor_removed_at {
+            self.step - floor_removed_at
+        } else {
+            return;
+        };
+
+        let steps_per_second = self.config.steps_per_second as f32;
+        let timed_out = steps_since_drop as f32 >= self.config.timeout_seconds * steps_per_second;
+        let all_idle = self.physics.ball_tracker.all_idle_steps() as f32
+            >= self.config.idle_seconds * steps_per_second;
+
+        if !timed_out && !all_idle {
+            return;
+        }
+
+        for id in self.physics.ball_tracker.ids() {
+            if !self.finished.contains(&id) {
+                self.send(Event::BallStuck(id));
+            }
+        }
+
+        if all_idle
+            && !timed_out
+            && self.config.stuck_action == StuckAction::Nudge
+            && self.nudges < self.config.max_nudges
+        {
+            self.nudges += 1;
+            self.physics
+                .nudge_balls(&mut self.rng, self.config.nudge_speed);
+            return;
+        }
+
+        if let Some(id) = self.physics.ball_tracker.lowest() {
+            self.declare_winner(id);
+        }
+    }
+
+    fn create_choice_balls(&mut self, choices: &[Choice]) {
+        for (index, choice) in choices.iter().enumerate() {
+            let radius = choice.radius.unwrap_or(self.config.choice_radius);
+            let restitution = choice.restitution.unwrap_or(self.config.bounciness);
+            let density = choice.density.unwrap_or(self.config.choice_density);
+            let friction = choice.friction.unwrap_or(self.config.choice_friction);
+            let start_x = self.config.wall_width + radius * 2.0;
+            let end_x = self.config.width - self.config.wall_width - radius * 2.0;
+            let start_y = -(choices.len() as f32 * radius);
+            let end_y = 0.0;
+            let position = Vector2::new_random(&mut self.rng, start_x..end_x, start_y..end_y);
+            let id = self
+                .physics
+                .insert_ball(position, radius, restitution, density, friction);
+            let color = choice
+                .color()
+                .unwrap_or_else(|| default_choice_color(index, self.config.accessibility));
+            self.draw_data.insert_color(id, color);
+            self.draw_data.insert_type(id, DataType::Ball);
+            self.draw_data.insert_name(id, choice.name.clone());
+            self.draw_data.insert_radius(id, radius);
+            self.choice_ids.push(id);
+            if let Some(image) = &choice.image {
+                self.draw_data.insert_image(id, image.clone());
+            }
+        }
+    }
+
+    fn insert_floor(&mut self) {
+        let position = Vector2::new(self.config.width / 2.0, self.config.floor_position_y);
+        let id = self
+            .physics
+            .insert_wall(position, self.config.width, self.config.floor_height);
+        self.draw_data.insert_type(id, DataType::Wall);
+        self.draw_data.insert_color(id, self.config.floor_color);
+        let rect = Rect::new(
+            0.0,
+            self.config.floor_position_y - self.config.floor_height / 2.0,
+            self.config.width,
+            self.config.floor_height,
+        );
+        self.draw_data.insert_rectangle(id, rect);
+        self.floor_id = Some(id);
+    }
+
+    fn create_nails(&mut self) {
+        let mut y = self.config.floor_position_y * 2.0;
+        let space_between_x = self.config.width / self.config.nails_in_row as f32;
+        let space_between_y = (self.config.height - y) / self.config.rows_of_nails as f32;
+
+        for y_count in 0..self.config.rows_of_nails {
+            for x_count in 0..self.config.nails_in_row {
+                let offset = if y_count % 2 == 0 {
+                    space_between_x / 2.0
+                } else {
+                    0.0
+                };
+                let position = Vector2::new(space_between_x * x_count as f32 + offset, y);
+                let id = self.physics.insert_nail(position, self.config.nail_radius);
+                self.draw_data.insert_type(id, DataType::Nail);
+            }
+            y += space_between_y;
+        }
+    }
+
+    fn create_walls(&mut self) {
+        let left_position = Vector2::new(self.config.wall_width / 2.0, self.config.height / 2.0);
+        let left_id = self.physics.insert_wall(
+            left_position,
+            self.config.wall_width,
+            self.config.height * 2.0,
+        );
+        let right_position = Vector2::new(
+            self.config.width - self.config.wall_width / 2.0,
+            self.config.height / 2.0,
+        );
+        let right_id = self.physics.insert_wall(
+            right_position,
+            self.config.wall_width,
+            self.config.height * 2.0,
+        );
+        self.draw_data.insert_type(left_id, DataType::Wall);
+        self.draw_data.insert_type(right_id, DataType::Wall);
+        self.draw_data.insert_rectangle(
+            left_id,
+            Rect::new(0.0, 0.0, self.config.wall_width, self.config.height),
+        );
+        self.draw_data.insert_rectangle(
+            right_id,
+            Rect::new(
+                self.config.width - self.config.wall_width,
+                0.0,
+                self.config.wall_width,
+                self.config.height,
+            ),
+        );
+        self.draw_data.insert_color(left_id, self.config.wall_color);
+        self.draw_data
+            .insert_color(right_id, self.config.wall_color);
+    }
+
+    fn create_collector(&mut self, is_right: bool) {
+        let mut position = Vector2::new(
+            self.config.width / 4.0,
+            self.config.height - self.config.collector_offset_y,
+        );
+        if is_right {
+            *position.get_x_mut() += self.config.width / 2.0 + self.config.choice_radius * 4.0;
+        } else {
+            *position.get_x_mut() -= self.config.choice_radius;
+        }
+        let width = self.config.width / 2.0;
+        let height = self.config.wall_width;
+        let rotation = if is_right {
+            -self.config.collector_rotation
+        } else {
+            self.config.collector_rotation
+        };
+        let draw_type = DataType::Collector;
+        let id = self.physics.insert_rotated_wall(
+            position,
+            width,
+            height,
+            rotation,
+            -self.config.collector_rotation_offset,
+        );
+
+        let rect_width = width + self.config.collector_rotation_offset;
+        let draw_rect = if is_right {
+            Rect::new(
+                self.config.width / 2.0 + self.config.choice_radius,
+                self.config.height + self.config.collector_offset_y * 0.5,
+                rect_width,
+                height,
+            )
+        } else {
+            Rect::new(
+                0.0 - self.config.choice_radius,
+                self.config.height - self.config.collector_offset_y,
+                rect_width,
+                height,
+            )
+        };
+
+        self.draw_data.insert_rectangle(id, draw_rect);
+        self.draw_data.insert_rotation(id, rotation);
+        self.draw_data.insert_type(id, draw_type);
+    }
+
+    fn create_winning_sensor(&mut self) {
+        let sensor_width = self.config.width;
+        let sensor_height = self.config.choice_radius;
+        let sensor_position = Vector2::new(
+            self.config.width / 2.0,
+            self.config.height + sensor_height / 2.0,
+        );
+
+        let _id = self
+            .physics
+            .insert_sensor(sensor_position, sensor_width, sensor_height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event_manager::event::EventKind;
+
+    use super::*;
+
+    fn ball_positions(simulation: &Simulation) -> Vec<(u128, f32, f32)> {
+        let ball_tracker = &simulation.physics.ball_tracker;
+        ball_tracker
+            .ids()
+            .into_iter()
+            .map(|id| {
+                let ball = ball_tracker.get(id).unwrap();
+                (id, ball.x, ball.y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_trajectories() {
+        let mut config = Config::default();
+        config.seed = Some(7);
+        let choices: Vec<Choice> = (0..5).map(|index| Choice::new(index.to_string())).collect();
+        let drop_at_step = 30;
+
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut event_manager = EventManager::new();
+            let events = event_manager.subscribe(EventKind::Intersection);
+            let mut simulation = Simulation::new(&config, &mut event_manager);
+            simulation.setup(&choices);
+            runs.push((simulation, event_manager, events));
+        }
+
+        for _ in 0..600 {
+            for (simulation, event_manager, events) in runs.iter_mut() {
+                simulation
+                    .advance(event_manager, events, drop_at_step)
+                    .unwrap();
+            }
+            assert_eq!(ball_positions(&runs[0].0), ball_positions(&runs[1].0));
+        }
+        let decided = |simulation: &Simulation| {
+            simulation
+                .outcome()
+                .map(|outcome| (outcome.winner, outcome.steps))
+        };
+        assert_eq!(decided(&runs[0].0), decided(&runs[1].0));
+    }
+
+    #[test]
+    fn balls_resting_on_the_floor_before_the_drop_are_not_stuck() {
+        let mut config = Config::default();
+        config.stuck_action = StuckAction::Tiebreak;
+        config.seed = Some(1);
+        let choices = vec![Choice::new("a"), Choice::new("b"), Choice::new("c")];
+        // Long enough for the balls to settle and sit idle for longer than `idle_seconds`
+        let drop_at_step = ((config.idle_seconds + 2.0) * config.steps_per_second as f32) as u64;
+
+        let mut event_manager = EventManager::new();
+        let events = event_manager.subscribe(EventKind::Intersection);
+        let mut simulation = Simulation::new(&config, &mut event_manager);
+        simulation.setup(&choices);
+        while simulation.floor_removed_at().is_none() {
+            simulation
+                .advance(&mut event_manager, &events, drop_at_step)
+                .unwrap();
+        }
+
+        assert_eq!(simulation.current_step(), drop_at_step + 1);
+        assert!(simulation.outcome().is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Image, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+
+use crate::config::config_struct::{Config, WinnerReveal};
+use crate::helpers::create_winner_text::create_winner_text;
+
+const AVATAR_SPACING: f32 = 20.0;
+
+/// The overlay announcing the winner, drawn in screen coordinates on top of the board.
+pub struct WinnerBanner {
+    message: String,
+    color: Color,
+    avatar: Option<Image>,
+    elapsed: f32,
+}
+
+impl WinnerBanner {
+    pub fn new(message: String, color: Color, avatar: Option<Image>) -> Self {
+        Self {
+            message,
+            color,
+            avatar,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn draw(&self, context: &mut Context, config: &Config, font: Font) -> GameResult {
+        let (width, height) = graphics::drawable_size(context);
+        let background_mesh = MeshBuilder::new()
+            .rectangle(
+                DrawMode::fill(),
+                Rect::new(0.0, 0.0, width, height),
+                config.winning_background_color(&self.color),
+            )
+            .build(context)?;
+        graphics::draw(context, &background_mesh, DrawParam::new())?;
+
+        let progress = if config.winner_reveal_seconds > 0.0 {
+            (self.elapsed / config.winner_reveal_seconds).min(1.0)
+        } else {
+            1.0
+        };
+        let scale = match config.winner_reveal {
+            WinnerReveal::Scale => 1.0 - (1.0 - progress).powi(3),
+            _ => 1.0,
+        };
+        let mut visible_characters = match config.winner_reveal {
+            WinnerReveal::Typewriter => {
+                (self.message.chars().count() as f32 * progress).ceil() as usize
+            }
+            _ => usize::MAX,
+        };
+
+        // Lines are laid out at full length so they don't shift while being typed out.
+        let lines: Vec<(&str, f32, f32)> = self
+            .message
+            .lines()
+            .map(|line| {
+                let text = create_winner_text(line, font, config.winner_font_size);
+                let (line_width, line_height) = text.dimensions(context);
+                let line_height = (line_height as f32).max(config.winner_font_size);
+                (line, line_width as f32, line_height)
+            })
+            .collect();
+        let block_height: f32 = lines.iter().map(|(_, _, line_height)| line_height).sum();
+        let center_x = width * config.winner_position[0];
+        let center_y = height * config.winner_position[1];
+        let scaled = |x: f32, y: f32| {
+            [
+                center_x + (x - center_x) * scale,
+                center_y + (y - center_y) * scale,
+            ]
+        };
+
+        let mut y = center_y - block_height / 2.0;
+        if let Some(avatar) = &self.avatar {
+            graphics::draw(
+                context,
+                avatar,
+                DrawParam::new()
+                    .dest(scaled(
+                        center_x - avatar.width() as f32 / 2.0,
+                        y - avatar.height() as f32 - AVATAR_SPACING,
+                    ))
+                    .scale([scale, scale]),
+            )?;
+        }
+        for (line, line_width, line_height) in lines {
+            let visible: String = line.chars().take(visible_characters).collect();
+            visible_characters = visible_characters.saturating_sub(line.chars().count() + 1);
+            let text = create_winner_text(&visible, font, config.winner_font_size);
+            graphics::draw(
+                context,
+                &text,
+                DrawParam::new()
+                    .dest(scaled(center_x - line_width / 2.0, y))
+                    .scale([scale, scale])
+                    .color(self.color),
+            )?;
+            y += line_height;
+        }
+        Ok(())
+    }
+}
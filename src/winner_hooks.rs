@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use eyre::{bail, Result};
+use serde_json::json;
+
+use crate::choices::Choice;
+use crate::config::config_struct::Config;
+use crate::observer::DropObserver;
+
+/// Tells the outside world about the winner by running the configured `on_winner` command
+/// and posting to `winner_webhook`.
+pub struct WinnerHooks {
+    command: Option<String>,
+    webhook: Option<String>,
+    /// Hooks started from the window, waited for when the window closes.
+    running: Vec<JoinHandle<()>>,
+}
+
+impl WinnerHooks {
+    /// `None` when neither hook is configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.on_winner.is_none() && config.winner_webhook.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            command: config.on_winner.clone(),
+            webhook: config.winner_webhook.clone(),
+            running: vec![],
+        })
+    }
+
+    /// Runs both hooks and waits for them. Failures are reported but never stop the drop.
+    pub fn notify(&self, winner: &Choice, rank: usize) {
+        let payload = json!({
+            "name": winner.name,
+            "rank": rank,
+            "group": winner.group,
+            "choice": winner,
+        })
+        .to_string();
+
+        if let Some(command) = &self.command {
+            if let Err(error) = run_command(command, winner, rank, &payload) {
+                eprintln!("The on_winner command failed: {}", error);
+            }
+        }
+        if let Some(webhook) = &self.webhook {
+            if let Err(error) = post(webhook, &payload) {
+                eprintln!("Could not post the winner to {}: {}", webhook, error);
+            }
+        }
+    }
+}
+
+impl DropObserver for WinnerHooks {
+    /// Hooks run on their own thread so a slow command or server doesn't freeze the window.
+    fn winner_declared(&mut self, choice: &Choice) {
+        let hooks = Self {
+            command: self.command.clone(),
+            webhook: self.webhook.clone(),
+            running: vec![],
+        };
+        let choice = choice.clone();
+        self.running
+            .push(thread::spawn(move || hooks.notify(&choice, 1)));
+    }
+
+    /// Waits for the hooks so the process doesn't exit in the middle of running them.
+    fn finished(&mut self) {
+        for hook in self.running.drain(..) {
+            if hook.join().is_err() {
+                eprintln!("A winner hook panicked");
+            }
+        }
+    }
+}
+
+/// The winner is passed in `DROP_WINNER_*` environment variables and as JSON on stdin.
+fn run_command(command: &str, winner: &Choice, rank: usize, payload: &str) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .env("DROP_WINNER_NAME", &winner.name)
+        .env("DROP_WINNER_RANK", rank.to_string())
+        .env("DROP_WINNER_GROUP", winner.group.as_deref().unwrap_or(""))
+        .env("DROP_WINNER_JSON", payload)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Commands that ignore stdin may close it before we're done writing, which is fine
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {}", command, status);
+    }
+    Ok(())
+}
+
+fn post(url: &str, payload: &str) -> Result<()> {
+    ureq::post(url)
+        .set("Content-Type", "application/json")
+        .send_string(payload)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, remove_file};
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("winner_hooks_test_{}_{}", std::process::id(), name))
+    }
+
+    fn winner() -> Choice {
+        let mut choice = Choice::new("Ada");
+        choice.group = Some("red".to_owned());
+        choice
+    }
+
+    fn assert_payload(payload: &Value) {
+        assert_eq!(payload["name"], "Ada");
+        assert_eq!(payload["rank"], 1);
+        assert_eq!(payload["group"], "red");
+        assert_eq!(payload["choice"]["name"], "Ada");
+    }
+
+    #[test]
+    fn no_hooks_configured() {
+        assert!(WinnerHooks::new(&Config::default()).is_none());
+    }
+
+    #[test]
+    fn webhook_receives_the_winner() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let mut config = Config::default();
+        config.winner_webhook = Some(format!("http://{}/winner", server.server_addr()));
+        let hooks = WinnerHooks::new(&config).unwrap();
+
+        let receiver = thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            request.respond(tiny_http::Response::empty(200)).unwrap();
+            body
+        });
+        hooks.notify(&winner(), 1);
+
+        let body = receiver.join().unwrap();
+        assert_payload(&serde_json::from_str(&body).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_receives_the_winner() {
+        let env_path = temp_path("env");
+        let stdin_path = temp_path("stdin");
+        let mut config = Config::default();
+        config.on_winner = Some(format!(
+            "printf '%s\\n%s\\n%s\\n%s\\n' \"$DROP_WINNER_NAME\" \"$DROP_WINNER_RANK\" \
+             \"$DROP_WINNER_GROUP\" \"$DROP_WINNER_JSON\" > '{}' && cat > '{}'",
+            env_path.display(),
+            stdin_path.display()
+        ));
+        let hooks = WinnerHooks::new(&config).unwrap();
+
+        hooks.notify(&winner(), 1);
+
+        let env = read_to_string(&env_path).unwrap();
+        let lines: Vec<&str> = env.lines().collect();
+        assert_eq!(lines[..3], ["Ada", "1", "red"]);
+        assert_payload(&serde_json::from_str(lines[3]).unwrap());
+        assert_payload(&serde_json::from_str(&read_to_string(&stdin_path).unwrap()).unwrap());
+        remove_file(env_path).unwrap();
+        remove_file(stdin_path).unwrap();
+    }
+}